@@ -0,0 +1,114 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AvatarProvider {
+    DiceBear,
+    Initials,
+}
+
+const INITIALS_COLORS: &[&str] = &[
+    "#7c3aed", "#db2777", "#2563eb", "#059669", "#d97706", "#dc2626", "#0891b2", "#4f46e5",
+];
+
+fn initials(name: &str) -> String {
+    let mut words = name
+        .split(|c: char| c.is_whitespace() || c == '_' || c == '-' || c == '.')
+        .filter(|w| !w.is_empty());
+    let first = words.next().and_then(|w| w.chars().next());
+    let last = words.next_back().and_then(|w| w.chars().next());
+    first
+        .into_iter()
+        .chain(last)
+        .flat_map(char::to_uppercase)
+        .collect::<String>()
+        .replace(['<', '>', '&', '"', '\''], "")
+}
+
+// A stable colour per name, so a user keeps their colour across reloads.
+fn initials_color(name: &str) -> &'static str {
+    let hash = name
+        .bytes()
+        .fold(0u32, |hash, b| hash.wrapping_mul(31).wrapping_add(b as u32));
+    INITIALS_COLORS[hash as usize % INITIALS_COLORS.len()]
+}
+
+fn percent_encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for b in text.bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.~ =:/,()".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+fn initials_svg(name: &str) -> String {
+    format!(
+        "<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 64 64'>\
+         <rect width='64' height='64' fill='{}'/>\
+         <text x='50%' y='50%' dy='.35em' text-anchor='middle' font-family='sans-serif' \
+         font-size='26' fill='white'>{}</text></svg>",
+        initials_color(name),
+        initials(name)
+    )
+}
+
+pub fn avatar_src(provider: AvatarProvider, name: &str) -> String {
+    match provider {
+        AvatarProvider::DiceBear => format!(
+            "https://avatars.dicebear.com/api/adventurer-neutral/{}.svg",
+            name
+        ),
+        AvatarProvider::Initials => format!(
+            "data:image/svg+xml;charset=utf-8,{}",
+            percent_encode(&initials_svg(name))
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initials_provider_never_leaves_the_page() {
+        for name in ["alice", "Bob Smith", "a&b", "<img src=x>", ""] {
+            let src = avatar_src(AvatarProvider::Initials, name);
+            assert!(src.starts_with("data:image/svg+xml"), "{}", src);
+            assert!(!src.contains("dicebear"));
+        }
+    }
+
+    #[test]
+    fn data_uri_is_percent_encoded() {
+        let src = avatar_src(AvatarProvider::Initials, "alice");
+        let payload = src.split_once(',').unwrap().1;
+        assert!(payload.starts_with("%3Csvg"));
+        assert!(!payload.contains(['<', '>', '#', '\'']));
+    }
+
+    #[test]
+    fn initials_take_the_first_and_last_words() {
+        assert_eq!(initials("alice"), "A");
+        assert_eq!(initials("alice bob carol"), "AC");
+        assert_eq!(initials("john_doe"), "JD");
+        assert_eq!(initials("mary-jane.watson"), "MW");
+        assert_eq!(initials("émile zola"), "ÉZ");
+        assert_eq!(initials("   "), "");
+    }
+
+    #[test]
+    fn markup_characters_are_dropped_from_initials() {
+        assert_eq!(initials("<b> x"), "X");
+        assert_eq!(initials("&amp"), "");
+        assert_eq!(initials("\"quoted' 'name"), "");
+        let svg = initials_svg("<script> alert");
+        assert!(!svg.contains("<script"));
+    }
+
+    #[test]
+    fn colour_is_stable_per_name() {
+        assert_eq!(initials_color("alice"), initials_color("alice"));
+        assert!(INITIALS_COLORS.contains(&initials_color("bob")));
+    }
+}
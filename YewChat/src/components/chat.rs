@@ -1,20 +1,144 @@
-﻿use serde::{Deserialize, Serialize};
-use web_sys::HtmlInputElement;
+﻿use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use futures::channel::oneshot;
+use gloo::events::EventListener;
+use gloo::timers::callback::{Interval, Timeout};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{DataTransfer, Element, HtmlInputElement};
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
 
+use crate::components::avatar::{avatar_src, AvatarProvider};
+use crate::components::direction::detect_direction;
+use crate::components::emoji::{shortcode_candidates, shortcode_token};
+use crate::components::fuzzy::fuzzy_score;
+use crate::components::markdown::to_plaintext;
+use crate::components::mention::{highlight_own_name, mention_text};
+use crate::components::message::{
+    parse_message_data, push_message, user_display_name, MessageData,
+};
+use crate::components::truncate::{truncate_long_tokens, MAX_TOKEN_CHARS};
+use crate::components::utf16::{
+    paste_allowance, splice_utf16, truncate_utf16, utf16_to_byte_index,
+};
+use crate::components::version::{version_is_newer, CLIENT_VERSION};
+use crate::components::viewport::{current_keyboard_offset, visual_viewport};
+use crate::services::clipboard::copy_to_clipboard;
 use crate::services::event_bus::EventBus;
-use crate::{services::websocket::WebsocketService, User};
+use crate::services::frame_log::{log_frame, FrameDirection};
+use crate::services::settings::{BubbleWidth, Settings};
+use crate::services::websocket::{ConnectionListener, OutgoingFrame, WebsocketService};
+use crate::User;
 
 pub enum Msg {
     HandleMsg(String),
     SubmitMessage,
+    MessageListKeyDown(KeyboardEvent),
+    GlobalKeyDown(KeyboardEvent),
+    ToggleFocusMode,
+    TogglePalette,
+    PaletteInput(String),
+    PaletteKeyDown(KeyboardEvent),
+    RunPaletteAction(PaletteAction),
+    CopyUsername(String),
+    ComposerPaste(Event),
+    ShowNotice(String),
+    ClearNotice,
+    DismissUpdate,
+    SendFinished(bool),
+    ViewportChanged,
+    MentionUser(String),
+    SlowModeTick,
+    Moderate(ModerationAction, String),
+    ComposerInput,
+    ComposerKeyDown(KeyboardEvent),
+    PickEmoji(usize),
+    MessageTouchStart(usize),
+    MessageTouchEnd(usize),
+    MessageTouchCancel,
+    QuoteMessage(usize),
+    CopyMessage(usize, CopyFormat),
+    OpenActionSheet(usize),
+    CloseActionSheet,
+    ToggleCodeMode,
+    ExpireRecentlyJoined(String),
+    ToggleSettingsPanel,
+    SetSettings(Settings),
+    ExpandImage(usize),
+    ExpandLongMessage(usize),
+    BrowserOnline(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyFormat {
+    PlainText,
+    Markdown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shortcut {
+    JumpToBottom,
+    ToggleFocusMode,
+    TogglePalette,
+}
+
+fn message_list_shortcut(key: &str) -> Option<Shortcut> {
+    match key {
+        "End" => Some(Shortcut::JumpToBottom),
+        _ => None,
+    }
+}
+
+fn global_shortcut(key: &str, ctrl: bool, shift: bool) -> Option<Shortcut> {
+    if !(ctrl && shift) {
+        return None;
+    }
+    match key.to_ascii_lowercase().as_str() {
+        "f" => Some(Shortcut::ToggleFocusMode),
+        "p" => Some(Shortcut::TogglePalette),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteAction {
+    JumpToBottom,
+    ToggleFocusMode,
+    ToggleCodeMode,
+    OpenSettings,
+    MentionUser(String),
+}
+
+const MAX_PALETTE_RESULTS: usize = 8;
+
+fn rank_palette_actions(
+    query: &str,
+    actions: Vec<(String, PaletteAction)>,
+) -> Vec<(String, PaletteAction)> {
+    let mut scored: Vec<_> = actions
+        .into_iter()
+        .filter_map(|(label, action)| Some((fuzzy_score(query, &label)?, label, action)))
+        .collect();
+    scored.sort_by_key(|(score, _, _)| std::cmp::Reverse(*score));
+    scored
+        .into_iter()
+        .take(MAX_PALETTE_RESULTS)
+        .map(|(_, label, action)| (label, action))
+        .collect()
+}
+
+fn format_clock_time(time_ms: f64) -> String {
+    let date = js_sys::Date::new(&time_ms.into());
+    format!("{:02}:{:02}", date.get_hours(), date.get_minutes())
 }
 
-#[derive(Deserialize)]
-struct MessageData {
-    from: String,
-    message: String,
+fn format_timestamp(time_ms: f64) -> String {
+    let date = js_sys::Date::new(&time_ms.into());
+    String::from(date.to_locale_string("default", &wasm_bindgen::JsValue::UNDEFINED))
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -23,190 +147,2321 @@ pub enum MsgTypes {
     Users,
     Register,
     Message,
+    SlowMode,
+    Moderate,
+    ClientUpdate,
+    // Any type this client doesn't know; handed to `ChatProps::on_custom_frame`.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct CustomFrame {
+    pub message_type: String,
+    pub frame: Value,
+}
+
+fn custom_frame(raw: &str) -> Option<CustomFrame> {
+    let frame: Value = serde_json::from_str(raw).ok()?;
+    let message_type = frame.get("messageType")?.as_str()?.to_string();
+    Some(CustomFrame {
+        message_type,
+        frame,
+    })
+}
+
+// Hands an unknown frame to the embedder; false when nobody took it.
+fn dispatch_custom_frame(on_custom_frame: &Option<Callback<CustomFrame>>, raw: &str) -> bool {
+    match (on_custom_frame, custom_frame(raw)) {
+        (Some(on_custom_frame), Some(frame)) => {
+            on_custom_frame.emit(frame);
+            true
+        }
+        _ => {
+            log::debug!("ignoring frame of unknown type");
+            false
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct WebSocketMessage {
     message_type: MsgTypes,
-    data_array: Option<Vec<String>>,
-    data: Option<String>,
+    data_array: Option<Vec<Value>>,
+    data: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    roles: Option<HashMap<String, Role>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    #[serde(rename = "mod")]
+    Moderator,
+    #[default]
+    #[serde(other)]
+    Member,
+}
+
+impl Role {
+    fn label(&self) -> &'static str {
+        match self {
+            Role::Admin => "Admin",
+            Role::Moderator => "Moderator",
+            Role::Member => "Member",
+        }
+    }
+
+    fn badge(&self) -> Option<&'static str> {
+        match self {
+            Role::Admin => Some("👑"),
+            Role::Moderator => Some("🛡"),
+            Role::Member => None,
+        }
+    }
+
+    fn can_moderate(&self) -> bool {
+        matches!(self, Role::Admin | Role::Moderator)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationAction {
+    Kick,
+    Ban,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ModerationData {
+    action: ModerationAction,
+    target: String,
+    duration_minutes: Option<u32>,
+}
+
+#[derive(Debug, PartialEq)]
+enum BanAnswer {
+    Cancelled,
+    Permanent,
+    Minutes(u32),
+    Invalid,
+}
+
+// Reads the ban duration prompt: Cancel (None) aborts the ban, an empty
+// answer means a permanent ban, anything else must be a positive number of
+// minutes.
+fn parse_ban_answer(answer: Option<&str>) -> BanAnswer {
+    let answer = match answer {
+        Some(answer) => answer.trim(),
+        None => return BanAnswer::Cancelled,
+    };
+    if answer.is_empty() {
+        return BanAnswer::Permanent;
+    }
+    match answer.parse() {
+        Ok(0) | Err(_) => BanAnswer::Invalid,
+        Ok(minutes) => BanAnswer::Minutes(minutes),
+    }
+}
+
+fn can_moderate_user(role: Role, username: &str, target: &str) -> bool {
+    role.can_moderate() && username != target
+}
+
+fn moderation_message(
+    action: ModerationAction,
+    target: &str,
+    duration_minutes: Option<u32>,
+) -> WebSocketMessage {
+    let data = ModerationData {
+        action,
+        target: target.to_string(),
+        duration_minutes,
+    };
+    WebSocketMessage {
+        message_type: MsgTypes::Moderate,
+        data: Some(serde_json::to_string(&data).unwrap().into()),
+        data_array: None,
+        roles: None,
+    }
+}
+
+fn view_role_badge(role: Role) -> Html {
+    match role.badge() {
+        Some(badge) => html! {
+            <span class="ml-1" title={role.label()}>{badge}</span>
+        },
+        None => html! {},
+    }
+}
+
+fn view_repeat_badge(repeats: u32) -> Html {
+    if repeats == 0 {
+        return html! {};
+    }
+    html! {
+        <span class="ml-2 px-1.5 rounded-full bg-purple-100 text-xs text-purple-700" title="Sent more than once">
+            {format!("×{}", repeats + 1)}
+        </span>
+    }
+}
+
+fn slow_mode_remaining_secs(until_ms: f64, now_ms: f64) -> u32 {
+    if now_ms >= until_ms {
+        0
+    } else {
+        ((until_ms - now_ms) / 1000.0).ceil() as u32
+    }
+}
+
+fn can_submit(sending: bool, slow_mode_until_ms: f64, now_ms: f64) -> bool {
+    !sending && slow_mode_remaining_secs(slow_mode_until_ms, now_ms) == 0
+}
+
+const RECENTLY_JOINED_MS: u32 = 4000;
+
+fn newly_joined(previous: &[UserProfile], current: &[String]) -> Vec<String> {
+    current
+        .iter()
+        .filter(|name| !previous.iter().any(|u| &u.name == *name))
+        .cloned()
+        .collect()
+}
+
+// The user list; focus mode hides it along with the other chrome.
+fn view_sidebar(focus_mode: bool, users: Html) -> Html {
+    if focus_mode {
+        return html! {};
+    }
+    html! {
+        <div class="flex-none w-64 h-screen bg-gradient-to-b from-purple-700 to-purple-900 text-white overflow-auto">
+            <div class="text-2xl font-semibold p-4 border-b border-purple-500">{"👥 Users"}</div>
+            {users}
+        </div>
+    }
+}
+
+fn view_header(
+    focus_mode: bool,
+    toggle_focus_mode: Callback<MouseEvent>,
+    toggle_settings: Callback<MouseEvent>,
+    settings_panel: Html,
+) -> Html {
+    html! {
+        <div class="relative w-full h-16 bg-white shadow-md flex items-center px-6 border-b border-purple-200">
+            <div class="text-xl font-bold text-purple-800">{"💬 Purple Chat"}</div>
+            <button
+                onclick={toggle_focus_mode}
+                title={if focus_mode { "Exit focus mode (Ctrl+Shift+F)" } else { "Focus mode (Ctrl+Shift+F)" }}
+                class="ml-auto p-2 rounded-full text-purple-700 hover:bg-purple-100 transition"
+            >
+                {if focus_mode { "↩️" } else { "🎯" }}
+            </button>
+            {
+                if focus_mode {
+                    html! {}
+                } else {
+                    html! {
+                        <>
+                            <button
+                                onclick={toggle_settings}
+                                title="Settings"
+                                class="ml-1 p-2 rounded-full text-purple-700 hover:bg-purple-100 transition"
+                            >
+                                {"⚙️"}
+                            </button>
+                            {settings_panel}
+                        </>
+                    }
+                }
+            }
+        </div>
+    }
+}
+
+// The message pane's contents, one rendered row per message, with an empty
+// state until the first message arrives.
+fn view_messages(rows: Vec<Html>) -> Html {
+    if rows.is_empty() {
+        html! {
+            <div class="h-full flex items-center justify-center text-purple-300 text-lg">
+                {"No messages yet — say hi! 👋"}
+            </div>
+        }
+    } else {
+        rows.into_iter().collect::<Html>()
+    }
+}
+
+fn is_alone(users: &[UserProfile], username: &str) -> bool {
+    !users.is_empty() && users.iter().all(|u| u.name == username)
+}
+
+fn view_alone_hint(alone: bool) -> Html {
+    if alone {
+        html! {
+            <div class="w-full px-6 py-1 text-xs text-purple-400 bg-purple-50">
+                {"👋 You're the only one here right now."}
+            </div>
+        }
+    } else {
+        html! {}
+    }
+}
+
+// Drops a user's join highlight once its timer fires; true if one was shown.
+fn expire_recently_joined<T>(recently_joined: &mut HashMap<String, T>, name: &str) -> bool {
+    recently_joined.remove(name).is_some()
+}
+
+fn bubble_width_class(width: BubbleWidth) -> &'static str {
+    match width {
+        BubbleWidth::Narrow => "max-w-md",
+        BubbleWidth::Medium => "max-w-xl",
+        BubbleWidth::Wide => "max-w-3xl",
+        BubbleWidth::Full => "max-w-full",
+    }
+}
+
+fn wrap_code_block(text: &str) -> String {
+    format!("```\n{}\n```", text)
+}
+
+#[derive(Debug, PartialEq)]
+enum AfterSend {
+    Clear,
+    // Keep the text but select it, so the next keystroke replaces it.
+    SelectAll,
+    // The message never reached the server; leave the text for a retry.
+    Keep,
+}
+
+fn after_send(settings: &Settings, delivered: bool) -> AfterSend {
+    if !delivered {
+        AfterSend::Keep
+    } else if settings.keep_input_after_send {
+        AfterSend::SelectAll
+    } else {
+        AfterSend::Clear
+    }
+}
+
+// The frame text for the composer's value, or None when there is nothing
+// worth sending.
+fn outgoing_text(value: &str, code_mode: bool) -> Option<String> {
+    if value.trim().is_empty() {
+        None
+    } else if code_mode {
+        Some(wrap_code_block(value))
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn is_image_message(text: &str) -> bool {
+    text.ends_with(".gif")
+}
+
+fn image_filename(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/')
+        .find(|segment| !segment.is_empty())
+        .unwrap_or(path)
+}
+
+fn latest_image(messages: &[MessageData]) -> Option<usize> {
+    messages.iter().rposition(|m| is_image_message(&m.message))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ImageDisplay {
+    Full,
+    Thumbnail,
+    Collapsed,
+}
+
+// How an image message is shown, given whether the user expanded it and
+// whether it is the most recent image in the channel.
+fn image_display(settings: &Settings, expanded: bool, is_latest: bool) -> ImageDisplay {
+    let show_full = expanded
+        || if settings.expand_latest_image_only {
+            is_latest
+        } else {
+            !settings.collapse_images
+        };
+    if show_full {
+        ImageDisplay::Full
+    } else if settings.collapse_images {
+        ImageDisplay::Collapsed
+    } else {
+        ImageDisplay::Thumbnail
+    }
+}
+
+fn view_image(display: ImageDisplay, url: &str, expand: Callback<MouseEvent>) -> Html {
+    match display {
+        ImageDisplay::Full => html! { <img class="mt-2 rounded-md" src={url.to_string()} /> },
+        ImageDisplay::Collapsed => html! {
+            <button onclick={expand} class="mt-2 px-3 py-2 rounded-md bg-purple-100 text-purple-700 text-xs hover:bg-purple-200">
+                {format!("🖼 Show image ({})", image_filename(url))}
+            </button>
+        },
+        ImageDisplay::Thumbnail => html! {
+            <img
+                onclick={expand}
+                title={format!("Expand {}", image_filename(url))}
+                class="mt-2 w-16 h-16 object-cover rounded-md cursor-pointer"
+                src={url.to_string()}
+            />
+        },
+    }
+}
+
+// While a send is in flight the button shows a spinner and stays disabled
+// until the writer reports back.
+fn view_send_button(sending: bool, disabled: bool, onclick: Callback<MouseEvent>) -> Html {
+    html! {
+        <button
+            {onclick}
+            {disabled}
+            aria-busy={sending.to_string()}
+            class="ml-3 p-3 bg-purple-600 hover:bg-purple-700 disabled:opacity-50 rounded-full shadow-md transition"
+        >
+            {
+                if sending {
+                    html! {
+                        <svg viewBox="0 0 24 24" xmlns="http://www.w3.org/2000/svg" class="w-5 h-5 animate-spin">
+                            <circle cx="12" cy="12" r="9" stroke="white" stroke-width="3" fill="none" stroke-dasharray="42" stroke-linecap="round"/>
+                        </svg>
+                    }
+                } else {
+                    html! {
+                        <svg viewBox="0 0 24 24" xmlns="http://www.w3.org/2000/svg" class="w-5 h-5 fill-white">
+                            <path d="M0 0h24v24H0z" fill="none"/>
+                            <path d="M2.01 21L23 12 2.01 3 2 10l15 2-15 2z"/>
+                        </svg>
+                    }
+                }
+            }
+        </button>
+    }
+}
+
+// Records an online/offline event; true when the state actually flipped and
+// the banner needs re-rendering.
+fn set_browser_online(browser_online: &mut bool, online: bool) -> bool {
+    let changed = *browser_online != online;
+    *browser_online = online;
+    changed
+}
+
+fn view_offline_banner(browser_online: bool) -> Html {
+    if browser_online {
+        html! {}
+    } else {
+        html! {
+            <div class="w-full px-6 py-2 bg-gray-700 text-white text-sm text-center">
+                {"📴 You're offline. Check your network connection."}
+            </div>
+        }
+    }
+}
+
+// Everything view_message works out about a message before laying it out.
+struct MessageRow {
+    // Exact time shown as the bubble's tooltip.
+    title: Option<String>,
+    // Clock time for the timestamp gutter, or None when the gutter is off.
+    gutter: Option<String>,
+    avatar: String,
+    role: Role,
+    focus_mode: bool,
+    width: BubbleWidth,
+    ontouchstart: Callback<TouchEvent>,
+    ontouchend: Callback<TouchEvent>,
+    ontouchmove: Callback<TouchEvent>,
+    oncontextmenu: Callback<MouseEvent>,
+}
+
+fn view_message_row(m: &MessageData, row: MessageRow, body: Html) -> Html {
+    let MessageRow {
+        title,
+        gutter,
+        avatar,
+        role,
+        focus_mode,
+        width,
+        ontouchstart,
+        ontouchend,
+        ontouchmove,
+        oncontextmenu,
+    } = row;
+    let (row_class, gutter) = match gutter {
+        Some(time) => (
+            "grid grid-cols-[3.5rem_2rem_1fr] gap-3 items-start",
+            html! {
+                <div class="pt-3 text-right text-xs font-mono text-gray-400">{time}</div>
+            },
+        ),
+        None => ("flex items-start space-x-3", html! {}),
+    };
+    html! {
+        <div class={row_class}>
+            {gutter}
+            <img class="w-8 h-8 rounded-full" src={avatar} alt="avatar"/>
+            <div class={classes!("bg-white", "p-3", "rounded-xl", "shadow-sm", bubble_width_class(width))} {title} {ontouchstart} {ontouchend} {ontouchmove} {oncontextmenu}>
+                <div class="text-sm font-semibold text-purple-700">
+                    {m.from.clone()}
+                    {if focus_mode { html! {} } else { view_role_badge(role) }}
+                    {view_repeat_badge(m.repeats)}
+                </div>
+                <div class="text-sm text-gray-700 mt-1 break-words" dir={detect_direction(&m.message).attr()}>
+                    {body}
+                </div>
+            </div>
+        </div>
+    }
+}
+
+const LONG_PRESS_MS: f64 = 500.0;
+
+fn is_long_press(started_ms: f64, ended_ms: f64) -> bool {
+    ended_ms - started_ms >= LONG_PRESS_MS
+}
+
+const MAX_MESSAGE_LEN: u32 = 1000;
+const NOTICE_MS: u32 = 3000;
+
+fn insert_at_cursor(input: &HtmlInputElement, text: &str) {
+    let value = input.value();
+    let len = value.encode_utf16().count() as u32;
+    let start = input.selection_start().ok().flatten().unwrap_or(len);
+    let end = input.selection_end().ok().flatten().unwrap_or(start);
+    let (new_value, caret) = splice_utf16(&value, start, end, text);
+    input.set_value(&new_value);
+    let _ = input.set_selection_range(caret, caret);
 }
 
 #[derive(Clone)]
 struct UserProfile {
     name: String,
-    avatar: String,
+    role: Role,
+}
+
+type RenderFn = dyn Fn(&MessageData) -> Option<Html>;
+
+// A custom renderer for message bodies. Transforms are tried in order and the
+// first `Some` replaces the built-in rendering for that message.
+#[derive(Clone)]
+pub struct RenderTransform(Rc<RenderFn>);
+
+impl RenderTransform {
+    pub fn new(transform: impl Fn(&MessageData) -> Option<Html> + 'static) -> Self {
+        Self(Rc::new(transform))
+    }
+}
+
+impl PartialEq for RenderTransform {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// The body from the first transform that claims `m`, or None to fall back to
+// the built-in rendering.
+fn apply_render_transforms(transforms: &[RenderTransform], m: &MessageData) -> Option<Html> {
+    transforms.iter().find_map(|t| (t.0)(m))
+}
+
+#[derive(Properties, Clone, PartialEq, Default)]
+pub struct ChatProps {
+    #[prop_or_default]
+    pub on_connection_change: Option<ConnectionListener>,
+    #[prop_or_default]
+    pub on_custom_frame: Option<Callback<CustomFrame>>,
+    #[prop_or_default]
+    pub render_transforms: Vec<RenderTransform>,
 }
 
 pub struct Chat {
+    username: String,
     users: Vec<UserProfile>,
     chat_input: NodeRef,
+    message_list: NodeRef,
     _producer: Box<dyn Bridge<EventBus>>,
     wss: WebsocketService,
     messages: Vec<MessageData>,
+    slow_mode_secs: u32,
+    slow_mode_until_ms: f64,
+    now_ms: f64,
+    _slow_mode_tick: Option<Interval>,
+    emoji_candidates: Vec<(&'static str, &'static str)>,
+    emoji_selected: usize,
+    touch_started: Option<(usize, f64)>,
+    action_sheet_for: Option<usize>,
+    code_mode: bool,
+    notice: Option<(String, Timeout)>,
+    available_update: Option<String>,
+    sending: bool,
+    keyboard_offset_px: f64,
+    scroll_after_render: bool,
+    palette_open: bool,
+    palette_query: String,
+    palette_selected: usize,
+    palette_input: NodeRef,
+    _keydown_listener: EventListener,
+    _viewport_listeners: Vec<EventListener>,
+    recently_joined: HashMap<String, Timeout>,
+    settings: Settings,
+    settings_open: bool,
+    expanded_images: HashSet<usize>,
+    expanded_long_messages: HashSet<usize>,
+    latest_image: Option<usize>,
+    browser_online: bool,
+    _network_listeners: Vec<EventListener>,
 }
 impl Component for Chat {
     type Message = Msg;
-    type Properties = ();
+    type Properties = ChatProps;
 
     fn create(ctx: &Context<Self>) -> Self {
         let (user, _) = ctx
             .link()
             .context::<User>(Callback::noop())
             .expect("context to be set");
-        let wss = WebsocketService::new();
+        let wss = WebsocketService::new(ctx.props().on_connection_change.clone());
         let username = user.username.borrow().clone();
 
-        let message = WebSocketMessage {
+        let register = WebSocketMessage {
             message_type: MsgTypes::Register,
-            data: Some(username.to_string()),
+            data: Some(username.clone().into()),
             data_array: None,
+            roles: None,
         };
 
-        if let Ok(_) = wss
-            .tx
-            .clone()
-            .try_send(serde_json::to_string(&message).unwrap())
-        {
-            log::debug!("message sent successfully");
-        }
+        let window = web_sys::window().expect("no window");
+        let browser_online = window.navigator().on_line();
+        let network_listeners = [("online", true), ("offline", false)]
+            .into_iter()
+            .map(|(event, online)| {
+                let link = ctx.link().clone();
+                EventListener::new(&window, event, move |_| {
+                    link.send_message(Msg::BrowserOnline(online))
+                })
+            })
+            .collect();
+        let keydown_listener = {
+            let link = ctx.link().clone();
+            EventListener::new(&window, "keydown", move |e| {
+                if let Some(e) = e.dyn_ref::<KeyboardEvent>() {
+                    link.send_message(Msg::GlobalKeyDown(e.clone()))
+                }
+            })
+        };
+        let viewport_listeners = visual_viewport()
+            .map(|viewport| {
+                ["resize", "scroll"]
+                    .into_iter()
+                    .map(|event| {
+                        let link = ctx.link().clone();
+                        EventListener::new(&viewport, event, move |_| {
+                            link.send_message(Msg::ViewportChanged)
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
 
-        Self {
+        let chat = Self {
+            username,
             users: vec![],
             messages: vec![],
+            slow_mode_secs: 0,
+            slow_mode_until_ms: 0.0,
+            now_ms: 0.0,
+            _slow_mode_tick: None,
+            emoji_candidates: vec![],
+            emoji_selected: 0,
+            touch_started: None,
+            action_sheet_for: None,
+            code_mode: false,
+            notice: None,
+            available_update: None,
+            sending: false,
+            keyboard_offset_px: 0.0,
+            scroll_after_render: false,
+            palette_open: false,
+            palette_query: String::new(),
+            palette_selected: 0,
+            palette_input: NodeRef::default(),
+            _keydown_listener: keydown_listener,
+            _viewport_listeners: viewport_listeners,
+            recently_joined: HashMap::new(),
+            settings: Settings::load(),
+            settings_open: false,
+            expanded_images: HashSet::new(),
+            expanded_long_messages: HashSet::new(),
+            latest_image: None,
+            browser_online,
+            _network_listeners: network_listeners,
             chat_input: NodeRef::default(),
+            message_list: NodeRef::default(),
             wss,
             _producer: EventBus::bridge(ctx.link().callback(Msg::HandleMsg)),
-        }
+        };
+        chat.send(&register);
+        chat
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::HandleMsg(s) => {
-                let msg: WebSocketMessage = serde_json::from_str(&s).unwrap();
+                log_frame(self.settings.debug_frames, FrameDirection::Inbound, &s);
+                let msg: WebSocketMessage = match serde_json::from_str(&s) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        log::error!("invalid frame: {:?}", e);
+                        return false;
+                    }
+                };
                 match msg.message_type {
                     MsgTypes::Users => {
-                        let users_from_message = msg.data_array.unwrap_or_default();
+                        let users_from_message: Vec<String> = msg
+                            .data_array
+                            .unwrap_or_default()
+                            .iter()
+                            .filter_map(user_display_name)
+                            .collect();
+                        let roles = msg.roles.unwrap_or_default();
+                        if !self.users.is_empty() {
+                            for name in newly_joined(&self.users, &users_from_message) {
+                                let link = ctx.link().clone();
+                                let expired = name.clone();
+                                let timeout = Timeout::new(RECENTLY_JOINED_MS, move || {
+                                    link.send_message(Msg::ExpireRecentlyJoined(expired))
+                                });
+                                self.recently_joined.insert(name, timeout);
+                            }
+                        }
                         self.users = users_from_message
                             .iter()
                             .map(|u| UserProfile {
-                                name: u.into(),
-                                avatar: format!(
-                                    "https://avatars.dicebear.com/api/adventurer-neutral/{}.svg",
-                                    u
-                                )
-                                .into(),
+                                name: u.clone(),
+                                role: roles.get(u).copied().unwrap_or_default(),
                             })
                             .collect();
-                        return true;
+                        true
+                    }
+                    MsgTypes::Message => match parse_message_data(msg.data, msg.data_array) {
+                        Some(message_data) => {
+                            push_message(
+                                &mut self.messages,
+                                message_data,
+                                self.settings.merge_repeats,
+                            );
+                            self.latest_image = latest_image(&self.messages);
+                            true
+                        }
+                        None => {
+                            log::error!("message frame without valid message data");
+                            false
+                        }
+                    },
+                    MsgTypes::SlowMode => {
+                        self.slow_mode_secs = match msg.data {
+                            Some(Value::Number(n)) => {
+                                n.as_u64().and_then(|n| u32::try_from(n).ok()).unwrap_or(0)
+                            }
+                            Some(Value::String(d)) => d.trim().parse().unwrap_or(0),
+                            _ => 0,
+                        };
+                        if self.slow_mode_secs == 0 {
+                            self.slow_mode_until_ms = 0.0;
+                            self._slow_mode_tick = None;
+                        }
+                        true
                     }
-                    MsgTypes::Message => {
-                        let message_data: MessageData =
-                            serde_json::from_str(&msg.data.unwrap()).unwrap();
-                        self.messages.push(message_data);
-                        return true;
+                    MsgTypes::ClientUpdate => {
+                        let version = match msg.data {
+                            Some(Value::String(version)) => version,
+                            _ => return false,
+                        };
+                        let dismissed =
+                            self.settings.dismissed_update_version.as_ref() == Some(&version);
+                        if version_is_newer(&version, CLIENT_VERSION) && !dismissed {
+                            self.available_update = Some(version);
+                            true
+                        } else {
+                            false
+                        }
                     }
-                    _ => {
-                        return false;
+                    MsgTypes::Unknown => {
+                        dispatch_custom_frame(&ctx.props().on_custom_frame, &s);
+                        false
                     }
+                    _ => false,
                 }
             }
             Msg::SubmitMessage => {
-                let input = self.chat_input.cast::<HtmlInputElement>();
-                if let Some(input) = input {
-                    let message = WebSocketMessage {
-                        message_type: MsgTypes::Message,
-                        data: Some(input.value()),
-                        data_array: None,
-                    };
-                    if let Err(e) = self
-                        .wss
-                        .tx
-                        .clone()
-                        .try_send(serde_json::to_string(&message).unwrap())
-                    {
-                        log::debug!("error sending to channel: {:?}", e);
+                self.now_ms = js_sys::Date::now();
+                if !can_submit(self.sending, self.slow_mode_until_ms, self.now_ms) {
+                    return false;
+                }
+                let input = match self.chat_input.cast::<HtmlInputElement>() {
+                    Some(input) => input,
+                    None => return false,
+                };
+                let text = match outgoing_text(&input.value(), self.code_mode) {
+                    Some(text) => text,
+                    None => return false,
+                };
+                let message = WebSocketMessage {
+                    message_type: MsgTypes::Message,
+                    data: Some(text.into()),
+                    data_array: None,
+                    roles: None,
+                };
+                let written = match self.send_tracked(&message) {
+                    Some(written) => written,
+                    None => {
+                        ctx.link().send_message(Msg::SendFinished(false));
+                        return false;
                     }
-                    input.set_value("");
                 };
-                false
+                self.sending = true;
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let ok = written.await.unwrap_or(false);
+                    link.send_message(Msg::SendFinished(ok));
+                });
+                if self.slow_mode_secs > 0 {
+                    self.slow_mode_until_ms = self.now_ms + f64::from(self.slow_mode_secs) * 1000.0;
+                    let link = ctx.link().clone();
+                    self._slow_mode_tick = Some(Interval::new(1000, move || {
+                        link.send_message(Msg::SlowModeTick)
+                    }));
+                }
+                self.emoji_candidates.clear();
+                true
             }
-        }
-    }
-
-    fn view(&self, ctx: &Context<Self>) -> Html {
-        let submit = ctx.link().callback(|_| Msg::SubmitMessage);
-        html! {
-            <div class="flex w-screen font-sans">
-                <div class="flex-none w-64 h-screen bg-gradient-to-b from-purple-700 to-purple-900 text-white overflow-auto">
-                    <div class="text-2xl font-semibold p-4 border-b border-purple-500">{"👥 Users"}</div>
-                    {
-                        self.users.clone().iter().map(|u| {
-                            html!{
-                                <div class="flex m-3 bg-purple-800 rounded-xl p-3 items-center shadow-md hover:bg-purple-700 transition">
-                                    <img class="w-10 h-10 rounded-full border-2 border-white" src={u.avatar.clone()} alt="avatar"/>
-                                    <div class="ml-3">
-                                        <div class="text-sm font-medium">{u.name.clone()}</div>
-                                        <div class="text-xs text-purple-200">{"Active now"}</div>
-                                    </div>
-                                </div>
-                            }
-                        }).collect::<Html>()
-                    }
-                </div>
-                <div class="grow h-screen flex flex-col bg-purple-50">
-                    <div class="w-full h-16 bg-white shadow-md flex items-center px-6 border-b border-purple-200">
-                        <div class="text-xl font-bold text-purple-800">{"💬 Purple Chat"}</div>
-                    </div>
-                    <div class="flex-grow overflow-auto px-6 py-4 space-y-4">
-                        {
-                            self.messages.iter().map(|m| {
-                                let user = self.users.iter().find(|u| u.name == m.from);
-                                if let Some(user) = user {
-                                    html! {
-                                        <div class="flex items-start space-x-3">
-                                            <img class="w-8 h-8 rounded-full" src={user.avatar.clone()} alt="avatar"/>
-                                            <div class="bg-white p-3 rounded-xl shadow-sm max-w-xl">
-                                                <div class="text-sm font-semibold text-purple-700">{m.from.clone()}</div>
-                                                <div class="text-sm text-gray-700 mt-1">
-                                                    {
-                                                        if m.message.ends_with(".gif") {
-                                                            html! { <img class="mt-2 rounded-md" src={m.message.clone()} /> }
-                                                        } else {
-                                                            html! { { &m.message } }
-                                                        }
-                                                    }
-                                                </div>
-                                            </div>
-                                        </div>
-                                    }
-                                } else {
-                                    html! {}
+            Msg::ViewportChanged => {
+                let offset = current_keyboard_offset();
+                if (offset - self.keyboard_offset_px).abs() < 1.0 {
+                    return false;
+                }
+                self.scroll_after_render = offset > self.keyboard_offset_px;
+                self.keyboard_offset_px = offset;
+                true
+            }
+            Msg::SendFinished(delivered) => {
+                self.sending = false;
+                // The composer keeps the text and code mode until the write
+                // is confirmed, so a failed send can simply be retried.
+                match (
+                    after_send(&self.settings, delivered),
+                    self.chat_input.cast::<HtmlInputElement>(),
+                ) {
+                    (AfterSend::Keep, _) => ctx
+                        .link()
+                        .send_message(Msg::ShowNotice("Your message couldn't be sent.".into())),
+                    (AfterSend::SelectAll, Some(input)) => input.select(),
+                    (AfterSend::Clear, Some(input)) => input.set_value(""),
+                    (_, None) => {}
+                }
+                if delivered {
+                    self.code_mode = false;
+                }
+                true
+            }
+            Msg::ToggleCodeMode => {
+                self.code_mode = !self.code_mode;
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    let _ = input.focus();
+                }
+                true
+            }
+            Msg::Moderate(action, target) => {
+                if !can_moderate_user(self.current_role(), &self.username, &target) {
+                    return false;
+                }
+                let verb = match action {
+                    ModerationAction::Kick => "Kick",
+                    ModerationAction::Ban => "Ban",
+                };
+                if !gloo::dialogs::confirm(&format!("{} {}?", verb, target)) {
+                    return false;
+                }
+                let duration_minutes = match action {
+                    ModerationAction::Kick => None,
+                    ModerationAction::Ban => {
+                        let mut question =
+                            "Ban duration in minutes (leave empty for a permanent ban)";
+                        loop {
+                            let answer = gloo::dialogs::prompt(question, None);
+                            match parse_ban_answer(answer.as_deref()) {
+                                BanAnswer::Cancelled => return false,
+                                BanAnswer::Permanent => break None,
+                                BanAnswer::Minutes(minutes) => break Some(minutes),
+                                BanAnswer::Invalid => {
+                                    question = "Please enter a whole number of minutes, or leave it empty for a permanent ban"
                                 }
-                            }).collect::<Html>()
+                            }
                         }
-                    </div>
-                    <div class="w-full h-16 bg-white flex items-center px-4 border-t border-purple-200">
-                        <input
-                            ref={self.chat_input.clone()}
-                            type="text"
-                            placeholder="Type your message..."
-                            class="flex-grow px-4 py-2 rounded-full border border-purple-300 focus:outline-none focus:ring-2 focus:ring-purple-500 transition"
-                            required=true
-                        />
-                        <button
-                            onclick={submit}
-                            class="ml-3 p-3 bg-purple-600 hover:bg-purple-700 rounded-full shadow-md transition"
-                        >
-                            <svg viewBox="0 0 24 24" xmlns="http://www.w3.org/2000/svg" class="w-5 h-5 fill-white">
-                                <path d="M0 0h24v24H0z" fill="none"/>
-                                <path d="M2.01 21L23 12 2.01 3 2 10l15 2-15 2z"/>
-                            </svg>
-                        </button>
-                    </div>
-                </div>
-            </div>
-        }
-    }
-}
\ No newline at end of file
+                    }
+                };
+                self.send(&moderation_message(action, &target, duration_minutes));
+                false
+            }
+            Msg::ComposerInput => {
+                let had_candidates = !self.emoji_candidates.is_empty();
+                self.emoji_candidates = self
+                    .chat_input
+                    .cast::<HtmlInputElement>()
+                    .and_then(|input| {
+                        let value = input.value();
+                        let caret = self.caret_byte_index(&input, &value);
+                        shortcode_token(&value[..caret]).map(shortcode_candidates)
+                    })
+                    .unwrap_or_default();
+                self.emoji_selected = 0;
+                had_candidates || !self.emoji_candidates.is_empty()
+            }
+            Msg::ComposerPaste(e) => {
+                let (input, pasted) = match (
+                    self.chat_input.cast::<HtmlInputElement>(),
+                    js_sys::Reflect::get(&e, &"clipboardData".into())
+                        .ok()
+                        .and_then(|d| d.dyn_into::<DataTransfer>().ok())
+                        .and_then(|d| d.get_data("text").ok()),
+                ) {
+                    (Some(input), Some(pasted)) => (input, pasted),
+                    _ => return false,
+                };
+                let value = input.value();
+                let start = input.selection_start().ok().flatten().unwrap_or(0);
+                let end = input.selection_end().ok().flatten().unwrap_or(start);
+                let allowance = paste_allowance(
+                    value.encode_utf16().count() as u32,
+                    end.saturating_sub(start),
+                    MAX_MESSAGE_LEN,
+                );
+                if pasted.encode_utf16().count() as u32 <= allowance {
+                    return false;
+                }
+                e.prevent_default();
+                insert_at_cursor(&input, truncate_utf16(&pasted, allowance));
+                ctx.link().send_message(Msg::ShowNotice(format!(
+                    "Pasted text was cut to fit the {} character limit.",
+                    MAX_MESSAGE_LEN
+                )));
+                false
+            }
+            Msg::ShowNotice(text) => {
+                let link = ctx.link().clone();
+                let timeout = Timeout::new(NOTICE_MS, move || link.send_message(Msg::ClearNotice));
+                self.notice = Some((text, timeout));
+                true
+            }
+            Msg::ClearNotice => self.notice.take().is_some(),
+            Msg::DismissUpdate => {
+                if let Some(version) = self.available_update.take() {
+                    let mut settings = self.settings.clone();
+                    settings.dismissed_update_version = Some(version);
+                    ctx.link().send_message(Msg::SetSettings(settings));
+                }
+                true
+            }
+            Msg::ComposerKeyDown(e) => {
+                if self.emoji_candidates.is_empty() {
+                    return false;
+                }
+                let count = self.emoji_candidates.len();
+                match e.key().as_str() {
+                    "ArrowDown" => self.emoji_selected = (self.emoji_selected + 1) % count,
+                    "ArrowUp" => self.emoji_selected = (self.emoji_selected + count - 1) % count,
+                    "Enter" | "Tab" => {
+                        e.prevent_default();
+                        ctx.link().send_message(Msg::PickEmoji(self.emoji_selected));
+                        return false;
+                    }
+                    "Escape" => self.emoji_candidates.clear(),
+                    _ => return false,
+                }
+                e.prevent_default();
+                true
+            }
+            Msg::PickEmoji(index) => {
+                let emoji = match self.emoji_candidates.get(index) {
+                    Some((_, emoji)) => *emoji,
+                    None => return false,
+                };
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    let value = input.value();
+                    let caret = self.caret_byte_index(&input, &value);
+                    let token_start = value[..caret].rfind(':').unwrap_or(caret);
+                    let _ = input.focus();
+                    let _ = input.set_selection_range(
+                        value[..token_start].encode_utf16().count() as u32,
+                        value[..caret].encode_utf16().count() as u32,
+                    );
+                    insert_at_cursor(&input, emoji);
+                }
+                self.emoji_candidates.clear();
+                true
+            }
+            Msg::MessageTouchStart(index) => {
+                self.touch_started = Some((index, js_sys::Date::now()));
+                false
+            }
+            Msg::MessageTouchEnd(index) => match self.touch_started.take() {
+                Some((started_index, started_ms))
+                    if started_index == index && is_long_press(started_ms, js_sys::Date::now()) =>
+                {
+                    self.action_sheet_for = Some(index);
+                    true
+                }
+                _ => false,
+            },
+            Msg::MessageTouchCancel => {
+                self.touch_started = None;
+                false
+            }
+            Msg::QuoteMessage(index) => {
+                self.action_sheet_for = None;
+                if let (Some(m), Some(input)) = (
+                    self.messages.get(index),
+                    self.chat_input.cast::<HtmlInputElement>(),
+                ) {
+                    // Quotes always go in front of whatever is already typed.
+                    let _ = input.focus();
+                    let _ = input.set_selection_range(0, 0);
+                    insert_at_cursor(&input, &format!("> {} ", m.message));
+                }
+                true
+            }
+            Msg::CopyMessage(index, format) => {
+                self.action_sheet_for = None;
+                if let Some(m) = self.messages.get(index) {
+                    match format {
+                        CopyFormat::PlainText => copy_to_clipboard(&to_plaintext(&m.message)),
+                        CopyFormat::Markdown => copy_to_clipboard(&m.message),
+                    }
+                }
+                true
+            }
+            Msg::OpenActionSheet(index) => {
+                self.action_sheet_for = Some(index);
+                true
+            }
+            Msg::CloseActionSheet => {
+                self.action_sheet_for = None;
+                true
+            }
+            Msg::ExpireRecentlyJoined(name) => {
+                expire_recently_joined(&mut self.recently_joined, &name)
+            }
+            Msg::ToggleSettingsPanel => {
+                self.settings_open = !self.settings_open;
+                true
+            }
+            Msg::SetSettings(settings) => {
+                settings.save();
+                self.settings = settings;
+                true
+            }
+            Msg::ExpandImage(index) => self.expanded_images.insert(index),
+            Msg::ExpandLongMessage(index) => self.expanded_long_messages.insert(index),
+            Msg::BrowserOnline(online) => set_browser_online(&mut self.browser_online, online),
+            Msg::CopyUsername(name) => {
+                copy_to_clipboard(&name);
+                false
+            }
+            Msg::MentionUser(name) => {
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    let _ = input.focus();
+                    insert_at_cursor(&input, &mention_text(&name));
+                }
+                false
+            }
+            Msg::SlowModeTick => {
+                self.now_ms = js_sys::Date::now();
+                if self.now_ms >= self.slow_mode_until_ms {
+                    self._slow_mode_tick = None;
+                }
+                true
+            }
+            Msg::MessageListKeyDown(e) => {
+                if let Some(Shortcut::JumpToBottom) = message_list_shortcut(&e.key()) {
+                    e.prevent_default();
+                    self.scroll_to_bottom();
+                }
+                false
+            }
+            Msg::GlobalKeyDown(e) => {
+                match global_shortcut(&e.key(), e.ctrl_key(), e.shift_key()) {
+                    Some(Shortcut::ToggleFocusMode) => {
+                        ctx.link().send_message(Msg::ToggleFocusMode)
+                    }
+                    Some(Shortcut::TogglePalette) => ctx.link().send_message(Msg::TogglePalette),
+                    _ => return false,
+                }
+                e.prevent_default();
+                false
+            }
+            Msg::TogglePalette => {
+                self.palette_open = !self.palette_open;
+                self.palette_query.clear();
+                self.palette_selected = 0;
+                true
+            }
+            Msg::PaletteInput(query) => {
+                self.palette_query = query;
+                self.palette_selected = 0;
+                true
+            }
+            Msg::PaletteKeyDown(e) => {
+                let results = self.palette_results();
+                match e.key().as_str() {
+                    "ArrowDown" if !results.is_empty() => {
+                        self.palette_selected = (self.palette_selected + 1) % results.len();
+                    }
+                    "ArrowUp" if !results.is_empty() => {
+                        self.palette_selected =
+                            (self.palette_selected + results.len() - 1) % results.len();
+                    }
+                    "Enter" => {
+                        if let Some((_, action)) = results.into_iter().nth(self.palette_selected) {
+                            ctx.link().send_message(Msg::RunPaletteAction(action));
+                        }
+                    }
+                    "Escape" => ctx.link().send_message(Msg::TogglePalette),
+                    _ => return false,
+                }
+                e.prevent_default();
+                true
+            }
+            Msg::RunPaletteAction(action) => {
+                self.palette_open = false;
+                match action {
+                    PaletteAction::JumpToBottom => self.scroll_after_render = true,
+                    PaletteAction::ToggleFocusMode => ctx.link().send_message(Msg::ToggleFocusMode),
+                    PaletteAction::ToggleCodeMode => ctx.link().send_message(Msg::ToggleCodeMode),
+                    PaletteAction::OpenSettings => {
+                        // Leave focus mode through SetSettings: ToggleFocusMode
+                        // runs after this and would close the panel again.
+                        if self.settings.focus_mode {
+                            let settings = Settings {
+                                focus_mode: false,
+                                ..self.settings.clone()
+                            };
+                            ctx.link().send_message(Msg::SetSettings(settings));
+                        }
+                        self.settings_open = true;
+                    }
+                    PaletteAction::MentionUser(name) => {
+                        ctx.link().send_message(Msg::MentionUser(name))
+                    }
+                }
+                true
+            }
+            Msg::ToggleFocusMode => {
+                let mut settings = self.settings.clone();
+                settings.focus_mode = !settings.focus_mode;
+                ctx.link().send_message(Msg::SetSettings(settings));
+                self.settings_open = false;
+                false
+            }
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+        if std::mem::take(&mut self.scroll_after_render) {
+            self.scroll_to_bottom();
+        }
+        if let Some(input) = self.palette_input.cast::<HtmlInputElement>() {
+            let _ = input.focus();
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let submit = ctx.link().callback(|_| Msg::SubmitMessage);
+        let onkeydown = ctx.link().callback(Msg::MessageListKeyDown);
+        let composer_input = ctx.link().callback(|_: InputEvent| Msg::ComposerInput);
+        let composer_keydown = ctx.link().callback(Msg::ComposerKeyDown);
+        let toggle_code_mode = ctx.link().callback(|_| Msg::ToggleCodeMode);
+        let composer_class = classes!(
+            "flex-grow",
+            "px-4",
+            "py-2",
+            "rounded-full",
+            "border",
+            "border-purple-300",
+            "focus:outline-none",
+            "focus:ring-2",
+            "focus:ring-purple-500",
+            "transition",
+            self.code_mode.then_some("font-mono"),
+        );
+        let slow_mode_remaining = slow_mode_remaining_secs(self.slow_mode_until_ms, self.now_ms);
+        let focus_mode = self.settings.focus_mode;
+        html! {
+            <div class="flex w-screen font-sans">
+                {view_sidebar(focus_mode, self.users.iter().map(|u| self.view_user(ctx, u)).collect())}
+                <div
+                    class="grow h-screen flex flex-col bg-purple-50"
+                    style={format!("padding-bottom: {}px", self.keyboard_offset_px)}
+                >
+                    {view_header(
+                        focus_mode,
+                        ctx.link().callback(|_| Msg::ToggleFocusMode),
+                        ctx.link().callback(|_| Msg::ToggleSettingsPanel),
+                        self.view_settings_panel(ctx),
+                    )}
+                    {self.view_update_banner(ctx)}
+                    {view_offline_banner(self.browser_online)}
+                    <div
+                        ref={self.message_list.clone()}
+                        tabindex="0"
+                        {onkeydown}
+                        class="flex-grow overflow-auto px-6 py-4 space-y-4 focus:outline-none"
+                    >
+                        {view_messages(self.messages.iter().enumerate().map(|(i, m)| self.view_message(ctx, i, m)).collect())}
+                    </div>
+                    {view_alone_hint(is_alone(&self.users, &self.username) && !focus_mode)}
+                    <div class="relative w-full h-16 bg-white flex items-center px-4 border-t border-purple-200">
+                        {self.view_emoji_candidates(ctx)}
+                        <input
+                            ref={self.chat_input.clone()}
+                            oninput={composer_input}
+                            onkeydown={composer_keydown}
+                            onpaste={ctx.link().callback(Msg::ComposerPaste)}
+                            maxlength={MAX_MESSAGE_LEN.to_string()}
+                            type="text"
+                            placeholder={if self.code_mode { "Paste a code snippet..." } else { "Type your message..." }}
+                            class={composer_class}
+                            required=true
+                        />
+                        <button
+                            onclick={toggle_code_mode}
+                            title="Send as code block"
+                            class={classes!(
+                                "ml-3", "px-3", "py-2", "rounded-full", "font-mono", "text-sm", "transition",
+                                if self.code_mode { "bg-purple-600 text-white" } else { "bg-purple-100 text-purple-700 hover:bg-purple-200" },
+                            )}
+                        >
+                            {"</>"}
+                        </button>
+                        {
+                            if slow_mode_remaining > 0 {
+                                html! {
+                                    <div class="ml-3 text-xs text-purple-500 whitespace-nowrap">
+                                        {format!("🐢 Slow mode: {}s", slow_mode_remaining)}
+                                    </div>
+                                }
+                            } else {
+                                html! {}
+                            }
+                        }
+                        {view_send_button(
+                            self.sending,
+                            !can_submit(self.sending, self.slow_mode_until_ms, self.now_ms),
+                            submit,
+                        )}
+                    </div>
+                </div>
+                {self.view_action_sheet(ctx)}
+                {self.view_palette(ctx)}
+                {
+                    match &self.notice {
+                        Some((text, _)) => html! {
+                            <div class="fixed bottom-20 left-1/2 -translate-x-1/2 z-50 px-4 py-2 rounded-full bg-gray-800 text-white text-sm shadow-lg">
+                                {text}
+                            </div>
+                        },
+                        None => html! {},
+                    }
+                }
+            </div>
+        }
+    }
+}
+
+impl Chat {
+    fn send(&self, message: &WebSocketMessage) {
+        self.send_frame(message, None);
+    }
+
+    // Like `send`, but resolves to whether the socket write succeeded.
+    fn send_tracked(&self, message: &WebSocketMessage) -> Option<oneshot::Receiver<bool>> {
+        let (written, receiver) = oneshot::channel();
+        self.send_frame(message, Some(written)).then_some(receiver)
+    }
+
+    fn send_frame(
+        &self,
+        message: &WebSocketMessage,
+        written: Option<oneshot::Sender<bool>>,
+    ) -> bool {
+        let text = serde_json::to_string(message).unwrap();
+        log_frame(self.settings.debug_frames, FrameDirection::Outbound, &text);
+        match self
+            .wss
+            .tx
+            .clone()
+            .try_send(OutgoingFrame { text, written })
+        {
+            Ok(()) => true,
+            Err(e) => {
+                log::debug!("error sending to channel: {:?}", e);
+                false
+            }
+        }
+    }
+
+    fn caret_byte_index(&self, input: &HtmlInputElement, value: &str) -> usize {
+        match input.selection_start() {
+            Ok(Some(start)) => utf16_to_byte_index(value, start as usize),
+            _ => value.len(),
+        }
+    }
+
+    fn view_emoji_candidates(&self, ctx: &Context<Self>) -> Html {
+        if self.emoji_candidates.is_empty() {
+            return html! {};
+        }
+        html! {
+            <div class="absolute bottom-full left-4 mb-2 w-64 bg-white rounded-xl shadow-lg border border-purple-200 overflow-hidden">
+                {
+                    self.emoji_candidates.iter().enumerate().map(|(i, (code, emoji))| {
+                        let onmousedown = ctx.link().callback(move |e: MouseEvent| {
+                            e.prevent_default();
+                            Msg::PickEmoji(i)
+                        });
+                        let class = if i == self.emoji_selected {
+                            "flex items-center px-3 py-2 cursor-pointer bg-purple-100"
+                        } else {
+                            "flex items-center px-3 py-2 cursor-pointer hover:bg-purple-50"
+                        };
+                        html! {
+                            <div {class} {onmousedown}>
+                                <span class="text-lg">{*emoji}</span>
+                                <span class="ml-2 text-sm text-gray-700">{format!(":{}:", code)}</span>
+                            </div>
+                        }
+                    }).collect::<Html>()
+                }
+            </div>
+        }
+    }
+
+    fn view_message(&self, ctx: &Context<Self>, index: usize, m: &MessageData) -> Html {
+        let user = match self.users.iter().find(|u| u.name == m.from) {
+            Some(user) => user,
+            None => return html! {},
+        };
+        let ontouchstart = ctx
+            .link()
+            .callback(move |_: TouchEvent| Msg::MessageTouchStart(index));
+        let ontouchend = ctx
+            .link()
+            .callback(move |_: TouchEvent| Msg::MessageTouchEnd(index));
+        let ontouchmove = ctx.link().callback(|_: TouchEvent| Msg::MessageTouchCancel);
+        let oncontextmenu = ctx.link().callback(move |e: MouseEvent| {
+            e.prevent_default();
+            Msg::OpenActionSheet(index)
+        });
+        let body = if let Some(custom) = apply_render_transforms(&ctx.props().render_transforms, m)
+        {
+            custom
+        } else if is_image_message(&m.message) {
+            view_image(
+                image_display(
+                    &self.settings,
+                    self.expanded_images.contains(&index),
+                    self.latest_image == Some(index),
+                ),
+                &m.message,
+                ctx.link().callback(move |_| Msg::ExpandImage(index)),
+            )
+        } else {
+            self.view_message_text(ctx, index, m)
+        };
+        let row = MessageRow {
+            title: m.time.map(format_timestamp),
+            gutter: self
+                .settings
+                .timestamp_gutter
+                .then(|| m.time.map(format_clock_time).unwrap_or_default()),
+            avatar: self.avatar(&user.name),
+            role: user.role,
+            focus_mode: self.settings.focus_mode,
+            width: self.settings.bubble_width,
+            ontouchstart,
+            ontouchend,
+            ontouchmove,
+            oncontextmenu,
+        };
+        view_message_row(m, row, body)
+    }
+
+    fn view_message_text(&self, ctx: &Context<Self>, index: usize, m: &MessageData) -> Html {
+        let truncated = if self.expanded_long_messages.contains(&index) {
+            None
+        } else {
+            truncate_long_tokens(&m.message, MAX_TOKEN_CHARS)
+        };
+        let text = truncated.as_deref().unwrap_or(&m.message);
+        let body = if self.settings.highlight_own_name && m.from != self.username {
+            highlight_own_name(text, &self.username)
+        } else {
+            html! { { text } }
+        };
+        if truncated.is_none() {
+            return body;
+        }
+        let expand = ctx.link().callback(move |_| Msg::ExpandLongMessage(index));
+        html! {
+            <>
+                {body}
+                <button onclick={expand} class="block mt-1 text-xs text-purple-600 hover:underline">
+                    {"Show full message"}
+                </button>
+            </>
+        }
+    }
+
+    fn view_update_banner(&self, ctx: &Context<Self>) -> Html {
+        let version = match &self.available_update {
+            Some(version) => version,
+            None => return html! {},
+        };
+        let reload = Callback::from(|_| {
+            if let Some(window) = web_sys::window() {
+                let _ = window.location().reload();
+            }
+        });
+        let dismiss = ctx.link().callback(|_| Msg::DismissUpdate);
+        html! {
+            <div class="w-full px-6 py-2 bg-purple-600 text-white text-sm flex items-center">
+                <span>{format!("✨ A new version ({}) is available.", version)}</span>
+                <button onclick={reload} class="ml-3 px-3 py-1 rounded-full bg-white text-purple-700 font-medium">{"Reload"}</button>
+                <button onclick={dismiss} title="Dismiss" class="ml-auto px-2">{"✕"}</button>
+            </div>
+        }
+    }
+
+    fn view_setting_toggle(
+        &self,
+        ctx: &Context<Self>,
+        label: &'static str,
+        checked: bool,
+        toggle: fn(&mut Settings),
+    ) -> Html {
+        let settings = self.settings.clone();
+        let onchange = ctx.link().callback(move |_: Event| {
+            let mut settings = settings.clone();
+            toggle(&mut settings);
+            Msg::SetSettings(settings)
+        });
+        html! {
+            <label class="flex items-center justify-between py-1 text-sm text-gray-700 cursor-pointer">
+                <span>{label}</span>
+                <input type="checkbox" class="ml-4 accent-purple-600" {checked} {onchange}/>
+            </label>
+        }
+    }
+
+    fn view_bubble_width_picker(&self, ctx: &Context<Self>) -> Html {
+        let options = [
+            ("Narrow", BubbleWidth::Narrow),
+            ("Medium", BubbleWidth::Medium),
+            ("Wide", BubbleWidth::Wide),
+            ("Full", BubbleWidth::Full),
+        ];
+        html! {
+            <div class="py-1 text-sm text-gray-700">
+                <div>{"Message width"}</div>
+                <div class="mt-1 flex rounded-lg border border-purple-200 overflow-hidden">
+                    {
+                        options.into_iter().map(|(label, width)| {
+                            let mut settings = self.settings.clone();
+                            settings.bubble_width = width;
+                            let onclick = ctx.link().callback(move |_| Msg::SetSettings(settings.clone()));
+                            let class = if self.settings.bubble_width == width {
+                                "flex-1 py-1 text-xs bg-purple-600 text-white"
+                            } else {
+                                "flex-1 py-1 text-xs hover:bg-purple-50"
+                            };
+                            html! { <button {class} {onclick}>{label}</button> }
+                        }).collect::<Html>()
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    fn view_settings_panel(&self, ctx: &Context<Self>) -> Html {
+        if !self.settings_open {
+            return html! {};
+        }
+        html! {
+            <div class="absolute right-4 top-14 z-40 w-72 bg-white rounded-xl shadow-lg border border-purple-200 p-4">
+                <div class="text-sm font-semibold text-purple-800 mb-2">{"Settings"}</div>
+                {self.view_setting_toggle(ctx, "Collapse images", self.settings.collapse_images, |s| {
+                    s.collapse_images = !s.collapse_images
+                })}
+                {self.view_setting_toggle(ctx, "Only expand the latest image", self.settings.expand_latest_image_only, |s| {
+                    s.expand_latest_image_only = !s.expand_latest_image_only
+                })}
+                {self.view_setting_toggle(ctx, "Keep text after sending", self.settings.keep_input_after_send, |s| {
+                    s.keep_input_after_send = !s.keep_input_after_send
+                })}
+                {self.view_setting_toggle(ctx, "Highlight my name", self.settings.highlight_own_name, |s| {
+                    s.highlight_own_name = !s.highlight_own_name
+                })}
+                {self.view_setting_toggle(ctx, "Log frames to console", self.settings.debug_frames, |s| {
+                    s.debug_frames = !s.debug_frames
+                })}
+                {self.view_setting_toggle(ctx, "Timestamp column", self.settings.timestamp_gutter, |s| {
+                    s.timestamp_gutter = !s.timestamp_gutter
+                })}
+                {self.view_setting_toggle(ctx, "Local avatars only", self.settings.local_avatars, |s| {
+                    s.local_avatars = !s.local_avatars
+                })}
+                {self.view_setting_toggle(ctx, "Merge double-sent messages", self.settings.merge_repeats, |s| {
+                    s.merge_repeats = !s.merge_repeats
+                })}
+                {self.view_bubble_width_picker(ctx)}
+            </div>
+        }
+    }
+
+    fn view_action_sheet(&self, ctx: &Context<Self>) -> Html {
+        let index = match self.action_sheet_for {
+            Some(index) => index,
+            None => return html! {},
+        };
+        let quote = ctx.link().callback(move |_| Msg::QuoteMessage(index));
+        let copy_text = ctx
+            .link()
+            .callback(move |_| Msg::CopyMessage(index, CopyFormat::PlainText));
+        let copy_markdown = ctx
+            .link()
+            .callback(move |_| Msg::CopyMessage(index, CopyFormat::Markdown));
+        let close = ctx.link().callback(|_| Msg::CloseActionSheet);
+        html! {
+            <div class="fixed inset-0 z-50 flex items-end bg-black bg-opacity-30" onclick={close.clone()}>
+                <div class="w-full bg-white rounded-t-2xl p-4 space-y-2" onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}>
+                    <button onclick={quote} class="w-full py-3 rounded-xl bg-purple-100 text-purple-800 font-medium">{"Quote"}</button>
+                    <button onclick={copy_text} class="w-full py-3 rounded-xl bg-purple-100 text-purple-800 font-medium">{"Copy text"}</button>
+                    <button onclick={copy_markdown} class="w-full py-3 rounded-xl bg-purple-100 text-purple-800 font-medium">{"Copy markdown"}</button>
+                    <button onclick={close} class="w-full py-3 rounded-xl text-gray-500">{"Cancel"}</button>
+                </div>
+            </div>
+        }
+    }
+
+    fn palette_actions(&self) -> Vec<(String, PaletteAction)> {
+        let mut actions = vec![
+            (
+                "Jump to newest message".to_string(),
+                PaletteAction::JumpToBottom,
+            ),
+            (
+                if self.settings.focus_mode {
+                    "Exit focus mode"
+                } else {
+                    "Enter focus mode"
+                }
+                .to_string(),
+                PaletteAction::ToggleFocusMode,
+            ),
+            (
+                "Toggle code block mode".to_string(),
+                PaletteAction::ToggleCodeMode,
+            ),
+            ("Open settings".to_string(), PaletteAction::OpenSettings),
+        ];
+        actions.extend(
+            self.users
+                .iter()
+                .filter(|u| u.name != self.username)
+                .map(|u| {
+                    (
+                        format!("Mention @{}", u.name),
+                        PaletteAction::MentionUser(u.name.clone()),
+                    )
+                }),
+        );
+        actions
+    }
+
+    fn palette_results(&self) -> Vec<(String, PaletteAction)> {
+        rank_palette_actions(&self.palette_query, self.palette_actions())
+    }
+
+    fn view_palette(&self, ctx: &Context<Self>) -> Html {
+        if !self.palette_open {
+            return html! {};
+        }
+        let oninput = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::PaletteInput(input.value())
+        });
+        let onkeydown = ctx.link().callback(Msg::PaletteKeyDown);
+        let close = ctx.link().callback(|_| Msg::TogglePalette);
+        html! {
+            <div class="fixed inset-0 z-50 flex justify-center items-start pt-24 bg-black bg-opacity-30" onclick={close}>
+                <div class="w-96 bg-white rounded-xl shadow-xl overflow-hidden" onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}>
+                    <input
+                        ref={self.palette_input.clone()}
+                        {oninput}
+                        {onkeydown}
+                        value={self.palette_query.clone()}
+                        placeholder="Type a command..."
+                        class="w-full px-4 py-3 border-b border-purple-200 focus:outline-none"
+                    />
+                    {
+                        self.palette_results().into_iter().enumerate().map(|(i, (label, action))| {
+                            let onclick = ctx.link().callback(move |_| Msg::RunPaletteAction(action.clone()));
+                            let class = if i == self.palette_selected {
+                                "px-4 py-2 text-sm cursor-pointer bg-purple-100 text-purple-800"
+                            } else {
+                                "px-4 py-2 text-sm cursor-pointer text-gray-700 hover:bg-purple-50"
+                            };
+                            html! { <div {class} {onclick}>{label}</div> }
+                        }).collect::<Html>()
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    fn avatar(&self, name: &str) -> String {
+        let provider = if self.settings.local_avatars {
+            AvatarProvider::Initials
+        } else {
+            AvatarProvider::DiceBear
+        };
+        avatar_src(provider, name)
+    }
+
+    fn current_role(&self) -> Role {
+        self.users
+            .iter()
+            .find(|u| u.name == self.username)
+            .map(|u| u.role)
+            .unwrap_or_default()
+    }
+
+    fn view_user(&self, ctx: &Context<Self>, u: &UserProfile) -> Html {
+        let copy = {
+            let name = u.name.clone();
+            ctx.link()
+                .callback(move |_| Msg::CopyUsername(name.clone()))
+        };
+        let mention = if u.name != self.username {
+            let name = u.name.clone();
+            let onclick = ctx.link().callback(move |_| Msg::MentionUser(name.clone()));
+            html! {
+                <button {onclick} title="Mention" class="px-2 py-1 rounded bg-purple-600 hover:bg-purple-500">{"@"}</button>
+            }
+        } else {
+            html! {}
+        };
+        let moderation = if can_moderate_user(self.current_role(), &self.username, &u.name) {
+            let kick = {
+                let name = u.name.clone();
+                ctx.link()
+                    .callback(move |_| Msg::Moderate(ModerationAction::Kick, name.clone()))
+            };
+            let ban = {
+                let name = u.name.clone();
+                ctx.link()
+                    .callback(move |_| Msg::Moderate(ModerationAction::Ban, name.clone()))
+            };
+            html! {
+                <>
+                    <button onclick={kick} class="px-2 py-1 rounded bg-purple-600 hover:bg-purple-500">{"Kick"}</button>
+                    <button onclick={ban} class="px-2 py-1 rounded bg-red-600 hover:bg-red-500">{"Ban"}</button>
+                </>
+            }
+        } else {
+            html! {}
+        };
+        let class = if self.recently_joined.contains_key(&u.name) {
+            "flex m-3 bg-green-700 ring-2 ring-green-400 rounded-xl p-3 items-center shadow-md transition"
+        } else {
+            "flex m-3 bg-purple-800 rounded-xl p-3 items-center shadow-md hover:bg-purple-700 transition"
+        };
+        html! {
+            <div {class}>
+                <img class="w-10 h-10 rounded-full border-2 border-white" src={self.avatar(&u.name)} alt="avatar"/>
+                <div class="ml-3">
+                    <div class="text-sm font-medium">{u.name.clone()}{view_role_badge(u.role)}</div>
+                    <div class="text-xs text-purple-200">{"Active now"}</div>
+                </div>
+                <div class="ml-auto flex space-x-1 text-xs">
+                    <button onclick={copy} title="Copy username" class="px-2 py-1 rounded bg-purple-600 hover:bg-purple-500">{"📋"}</button>
+                    {mention}
+                    {moderation}
+                </div>
+            </div>
+        }
+    }
+
+    fn scroll_to_bottom(&self) {
+        if let Some(list) = self.message_list.cast::<Element>() {
+            list.set_scroll_top(list.scroll_height());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use yew::virtual_dom::VNode;
+
+    use super::*;
+
+    // Flattens rendered Html into an HTML-like string, with attributes sorted,
+    // so views can be checked without a DOM.
+    fn render(node: &Html) -> String {
+        match node {
+            VNode::VTag(tag) => {
+                let mut attributes: Vec<_> = tag.attributes.iter().collect();
+                attributes.sort();
+                let attributes: String = attributes
+                    .into_iter()
+                    .map(|(key, value)| format!(" {}=\"{}\"", key, value))
+                    .collect();
+                let children: String = tag.children().iter().map(render).collect();
+                format!("<{0}{1}>{2}</{0}>", tag.tag(), attributes, children)
+            }
+            VNode::VText(text) => text.text.to_string(),
+            VNode::VList(list) => list.iter().map(render).collect(),
+            _ => String::new(),
+        }
+    }
+
+    #[test]
+    fn staff_roles_have_badges() {
+        assert_eq!(Role::Admin.badge(), Some("👑"));
+        assert_eq!(Role::Moderator.badge(), Some("🛡"));
+        assert_eq!(Role::Member.badge(), None);
+    }
+
+    #[test]
+    fn only_staff_can_moderate() {
+        assert!(Role::Admin.can_moderate());
+        assert!(Role::Moderator.can_moderate());
+        assert!(!Role::Member.can_moderate());
+    }
+
+    #[test]
+    fn roles_parse_from_the_users_frame() {
+        let frame: WebSocketMessage = serde_json::from_str(
+            r#"{"messageType":"users","dataArray":["a","b","c","d"],
+                "roles":{"a":"admin","b":"mod","c":"member","d":"owner"}}"#,
+        )
+        .unwrap();
+        let roles = frame.roles.unwrap();
+        assert_eq!(roles["a"], Role::Admin);
+        assert_eq!(roles["b"], Role::Moderator);
+        assert_eq!(roles["c"], Role::Member);
+        assert_eq!(roles["d"], Role::Member);
+    }
+
+    #[test]
+    fn moderators_can_act_on_others_but_not_themselves() {
+        assert!(can_moderate_user(Role::Admin, "alice", "bob"));
+        assert!(can_moderate_user(Role::Moderator, "alice", "bob"));
+        assert!(!can_moderate_user(Role::Member, "alice", "bob"));
+        assert!(!can_moderate_user(Role::Admin, "alice", "alice"));
+    }
+
+    #[test]
+    fn cancelling_the_duration_prompt_aborts_the_ban() {
+        assert_eq!(parse_ban_answer(None), BanAnswer::Cancelled);
+    }
+
+    #[test]
+    fn only_an_empty_answer_means_permanent() {
+        assert_eq!(parse_ban_answer(Some("")), BanAnswer::Permanent);
+        assert_eq!(parse_ban_answer(Some("   ")), BanAnswer::Permanent);
+        assert_eq!(parse_ban_answer(Some("30")), BanAnswer::Minutes(30));
+        assert_eq!(parse_ban_answer(Some(" 5 ")), BanAnswer::Minutes(5));
+    }
+
+    #[test]
+    fn unparseable_durations_are_rejected() {
+        for answer in ["ten", "1.5", "-3", "0", "30m"] {
+            assert_eq!(
+                parse_ban_answer(Some(answer)),
+                BanAnswer::Invalid,
+                "{}",
+                answer
+            );
+        }
+    }
+
+    #[test]
+    fn moderation_frame_carries_action_target_and_duration() {
+        let frame =
+            serde_json::to_value(moderation_message(ModerationAction::Ban, "bob", Some(15)))
+                .unwrap();
+        assert_eq!(frame["messageType"], "moderate");
+        let data: Value = serde_json::from_str(frame["data"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            data,
+            serde_json::json!({"action": "ban", "target": "bob", "durationMinutes": 15})
+        );
+    }
+
+    #[test]
+    fn kick_frame_has_no_duration() {
+        let frame =
+            serde_json::to_value(moderation_message(ModerationAction::Kick, "bob", None)).unwrap();
+        let data: Value = serde_json::from_str(frame["data"].as_str().unwrap()).unwrap();
+        assert_eq!(data["action"], "kick");
+        assert_eq!(data["durationMinutes"], Value::Null);
+    }
+
+    #[test]
+    fn role_badge_renders_only_for_staff() {
+        assert_eq!(
+            render(&view_role_badge(Role::Admin)),
+            "<span class=\"ml-1\" title=\"Admin\">👑</span>"
+        );
+        assert_eq!(
+            render(&view_role_badge(Role::Moderator)),
+            "<span class=\"ml-1\" title=\"Moderator\">🛡</span>"
+        );
+        assert_eq!(render(&view_role_badge(Role::Member)), "");
+    }
+
+    #[test]
+    fn slow_mode_counts_down_in_whole_seconds() {
+        assert_eq!(slow_mode_remaining_secs(10_000.0, 0.0), 10);
+        assert_eq!(slow_mode_remaining_secs(10_000.0, 500.0), 10);
+        assert_eq!(slow_mode_remaining_secs(10_000.0, 1_000.0), 9);
+        assert_eq!(slow_mode_remaining_secs(10_000.0, 9_001.0), 1);
+    }
+
+    #[test]
+    fn slow_mode_is_over_at_the_deadline() {
+        assert_eq!(slow_mode_remaining_secs(10_000.0, 10_000.0), 0);
+        assert_eq!(slow_mode_remaining_secs(10_000.0, 12_000.0), 0);
+        assert_eq!(slow_mode_remaining_secs(0.0, 5_000.0), 0);
+    }
+
+    #[test]
+    fn sending_is_enabled_once_cooldown_ends_and_no_send_is_in_flight() {
+        assert!(!can_submit(false, 10_000.0, 9_999.0));
+        assert!(can_submit(false, 10_000.0, 10_000.0));
+        assert!(!can_submit(true, 0.0, 10_000.0));
+        assert!(can_submit(false, 0.0, 0.0));
+    }
+
+    #[test]
+    fn long_press_starts_at_the_threshold() {
+        assert!(!is_long_press(1_000.0, 1_000.0));
+        assert!(!is_long_press(1_000.0, 1_000.0 + LONG_PRESS_MS - 1.0));
+        assert!(is_long_press(1_000.0, 1_000.0 + LONG_PRESS_MS));
+        assert!(is_long_press(1_000.0, 3_000.0));
+    }
+
+    fn profiles(names: &[&str]) -> Vec<UserProfile> {
+        names
+            .iter()
+            .map(|name| UserProfile {
+                name: name.to_string(),
+                role: Role::default(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn newly_joined_lists_only_users_missing_before() {
+        let previous = profiles(&["alice", "bob"]);
+        let current = vec!["bob".to_string(), "carol".to_string(), "alice".to_string()];
+        assert_eq!(newly_joined(&previous, &current), vec!["carol".to_string()]);
+        assert!(newly_joined(&previous, &["alice".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn alone_hint_shows_only_when_nobody_else_is_here() {
+        let hint = |users: &[&str]| render(&view_alone_hint(is_alone(&profiles(users), "alice")));
+        assert!(hint(&["alice"]).contains("You're the only one here"));
+        assert_eq!(hint(&["alice", "bob"]), "");
+        // An empty list means the user list hasn't arrived yet.
+        assert_eq!(hint(&[]), "");
+    }
+
+    #[test]
+    fn empty_state_shows_until_the_first_message() {
+        assert!(render(&view_messages(vec![])).contains("No messages yet"));
+        let html = render(&view_messages(vec![html! { <p>{"hi"}</p> }]));
+        assert_eq!(html, "<p>hi</p>");
+    }
+
+    #[test]
+    fn join_highlight_is_removed_at_expiry() {
+        let mut recently_joined = HashMap::new();
+        recently_joined.insert("carol".to_string(), ());
+        recently_joined.insert("dave".to_string(), ());
+        assert!(expire_recently_joined(&mut recently_joined, "carol"));
+        assert!(!recently_joined.contains_key("carol"));
+        assert!(recently_joined.contains_key("dave"));
+        assert!(!expire_recently_joined(&mut recently_joined, "carol"));
+    }
+
+    #[test]
+    fn collapse_images_hides_images_behind_a_button_by_default() {
+        let settings = Settings {
+            collapse_images: true,
+            ..Settings::default()
+        };
+        let display = image_display(&settings, false, false);
+        assert_eq!(display, ImageDisplay::Collapsed);
+        let html = render(&view_image(
+            display,
+            "https://example.com/cat.gif",
+            Callback::noop(),
+        ));
+        assert!(html.starts_with("<button"));
+        assert!(html.contains("Show image (cat.gif)"));
+        assert!(!html.contains("<img"));
+    }
+
+    #[test]
+    fn expanding_one_image_leaves_the_others_collapsed() {
+        let settings = Settings {
+            collapse_images: true,
+            ..Settings::default()
+        };
+        assert_eq!(image_display(&settings, true, false), ImageDisplay::Full);
+        assert_eq!(
+            image_display(&settings, false, false),
+            ImageDisplay::Collapsed
+        );
+        assert_eq!(
+            image_display(&Settings::default(), false, false),
+            ImageDisplay::Full
+        );
+    }
+
+    fn text_message(message: &str) -> MessageData {
+        MessageData {
+            from: "alice".to_string(),
+            message: message.to_string(),
+            time: None,
+            repeats: 0,
+        }
+    }
+
+    #[test]
+    fn only_the_latest_image_expands_as_new_ones_arrive() {
+        let settings = Settings {
+            expand_latest_image_only: true,
+            ..Settings::default()
+        };
+        let mut messages = vec![text_message("hi")];
+        assert_eq!(latest_image(&messages), None);
+
+        messages.push(text_message("https://example.com/one.gif"));
+        assert_eq!(latest_image(&messages), Some(1));
+
+        messages.push(text_message("https://example.com/two.gif"));
+        messages.push(text_message("nice"));
+        let latest = latest_image(&messages);
+        assert_eq!(latest, Some(2));
+        assert_eq!(
+            image_display(&settings, false, latest == Some(2)),
+            ImageDisplay::Full
+        );
+        assert_eq!(
+            image_display(&settings, false, latest == Some(1)),
+            ImageDisplay::Thumbnail
+        );
+    }
+
+    #[test]
+    fn older_images_collapse_fully_when_collapse_images_is_on() {
+        let settings = Settings {
+            expand_latest_image_only: true,
+            collapse_images: true,
+            ..Settings::default()
+        };
+        assert_eq!(image_display(&settings, false, true), ImageDisplay::Full);
+        assert_eq!(
+            image_display(&settings, false, false),
+            ImageDisplay::Collapsed
+        );
+        assert_eq!(image_display(&settings, true, false), ImageDisplay::Full);
+    }
+
+    #[test]
+    fn offline_events_flip_the_state_once() {
+        let mut online = true;
+        assert!(set_browser_online(&mut online, false));
+        assert!(!online);
+        assert!(!set_browser_online(&mut online, false));
+        assert!(set_browser_online(&mut online, true));
+        assert!(online);
+    }
+
+    #[test]
+    fn offline_banner_shows_only_while_offline() {
+        assert!(render(&view_offline_banner(false)).contains("You're offline"));
+        assert_eq!(render(&view_offline_banner(true)), "");
+    }
+
+    fn message_row(title: Option<&str>, gutter: Option<&str>) -> MessageRow {
+        MessageRow {
+            title: title.map(str::to_string),
+            gutter: gutter.map(str::to_string),
+            avatar: "avatar.svg".to_string(),
+            role: Role::Moderator,
+            focus_mode: false,
+            width: BubbleWidth::default(),
+            ontouchstart: Callback::noop(),
+            ontouchend: Callback::noop(),
+            ontouchmove: Callback::noop(),
+            oncontextmenu: Callback::noop(),
+        }
+    }
+
+    #[test]
+    fn bubble_title_carries_the_exact_time() {
+        let title = "10/14/2026, 9:30:15 AM";
+        let html = render(&view_message_row(
+            &text_message("hi"),
+            message_row(Some(title), None),
+            html! { "hi" },
+        ));
+        assert!(html.contains(&format!("shadow-sm max-w-xl\" title=\"{}\">", title)));
+    }
+
+    #[test]
+    fn timestamp_gutter_switches_the_row_to_a_grid() {
+        let html = render(&view_message_row(
+            &text_message("hi"),
+            message_row(None, Some("09:30")),
+            html! { "hi" },
+        ));
+        assert!(html.starts_with(
+            "<div class=\"grid grid-cols-[3.5rem_2rem_1fr] gap-3 items-start\">\
+             <div class=\"pt-3 text-right text-xs font-mono text-gray-400\">09:30</div>"
+        ));
+    }
+
+    #[test]
+    fn rows_without_the_gutter_stay_flex() {
+        let html = render(&view_message_row(
+            &text_message("hi"),
+            message_row(None, None),
+            html! { "hi" },
+        ));
+        assert!(html.starts_with("<div class=\"flex items-start space-x-3\"><img"));
+        assert!(!html.contains("font-mono"));
+    }
+
+    #[test]
+    fn focus_mode_hides_the_chrome() {
+        let users = || html! { <div>{"alice"}</div> };
+        assert!(render(&view_sidebar(false, users())).contains("Users"));
+        assert_eq!(render(&view_sidebar(true, users())), "");
+
+        let header = |focus_mode| {
+            render(&view_header(
+                focus_mode,
+                Callback::noop(),
+                Callback::noop(),
+                html! { <div>{"panel"}</div> },
+            ))
+        };
+        assert!(header(false).contains("title=\"Settings\""));
+        assert!(header(false).contains("panel"));
+        assert!(!header(true).contains("title=\"Settings\""));
+        assert!(!header(true).contains("panel"));
+        assert!(header(true).contains("Exit focus mode"));
+
+        let badge = |focus_mode| {
+            let row = MessageRow {
+                focus_mode,
+                ..message_row(None, None)
+            };
+            render(&view_message_row(&text_message("hi"), row, html! { "hi" }))
+        };
+        assert!(badge(false).contains("title=\"Moderator\""));
+        assert!(!badge(true).contains("title=\"Moderator\""));
+    }
+
+    #[test]
+    fn bubble_has_no_title_without_a_time() {
+        let html = render(&view_message_row(
+            &text_message("hi"),
+            message_row(None, None),
+            html! { "hi" },
+        ));
+        assert!(html.contains("shadow-sm max-w-xl\">"));
+    }
+
+    #[test]
+    fn users_frame_with_mixed_entries_is_not_dropped() {
+        let raw =
+            r#"{"messageType":"users","dataArray":["alice",3,{"name":"bob"},null],"data":null}"#;
+        let msg: WebSocketMessage = serde_json::from_str(raw).unwrap();
+        let names: Vec<String> = msg
+            .data_array
+            .unwrap_or_default()
+            .iter()
+            .filter_map(user_display_name)
+            .collect();
+        assert_eq!(names, vec!["alice", "3", "bob"]);
+    }
+
+    #[test]
+    fn send_button_spins_and_disables_while_sending() {
+        let html = render(&view_send_button(
+            true,
+            !can_submit(true, 0.0, 0.0),
+            Callback::noop(),
+        ));
+        assert!(html.contains("disabled=\"disabled\""));
+        assert!(html.contains("aria-busy=\"true\""));
+        assert!(html.contains("animate-spin"));
+    }
+
+    #[test]
+    fn send_button_is_enabled_when_idle() {
+        let html = render(&view_send_button(
+            false,
+            !can_submit(false, 0.0, 0.0),
+            Callback::noop(),
+        ));
+        assert!(!html.contains("disabled=\"disabled\""));
+        assert!(html.contains("aria-busy=\"false\""));
+        assert!(!html.contains("animate-spin"));
+    }
+
+    fn palette(labels: &[&str]) -> Vec<(String, PaletteAction)> {
+        labels
+            .iter()
+            .map(|label| {
+                (
+                    label.to_string(),
+                    PaletteAction::MentionUser(label.to_string()),
+                )
+            })
+            .collect()
+    }
+
+    fn labels(ranked: Vec<(String, PaletteAction)>) -> Vec<String> {
+        ranked.into_iter().map(|(label, _)| label).collect()
+    }
+
+    #[test]
+    fn palette_ranks_best_matches_first_and_drops_misses() {
+        let actions = palette(&["Toggle code mode", "Open settings", "Jump to bottom"]);
+        assert_eq!(
+            labels(rank_palette_actions("set", actions.clone())),
+            vec!["Open settings"]
+        );
+        assert_eq!(
+            labels(rank_palette_actions("to", actions)),
+            vec!["Toggle code mode", "Jump to bottom"]
+        );
+    }
+
+    #[test]
+    fn empty_palette_query_keeps_order_up_to_the_limit() {
+        let names: Vec<String> = (0..12).map(|i| format!("Mention user{}", i)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let ranked = labels(rank_palette_actions("", palette(&names)));
+        assert_eq!(ranked.len(), MAX_PALETTE_RESULTS);
+        assert_eq!(ranked[0], "Mention user0");
+        assert_eq!(ranked[7], "Mention user7");
+    }
+
+    #[test]
+    fn palette_actions_keep_their_payload() {
+        let ranked = rank_palette_actions("bob", palette(&["alice", "bob"]));
+        assert_eq!(
+            ranked,
+            vec![(
+                "bob".to_string(),
+                PaletteAction::MentionUser("bob".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn each_bubble_width_maps_to_its_own_class() {
+        assert_eq!(bubble_width_class(BubbleWidth::Narrow), "max-w-md");
+        assert_eq!(bubble_width_class(BubbleWidth::Medium), "max-w-xl");
+        assert_eq!(bubble_width_class(BubbleWidth::Wide), "max-w-3xl");
+        assert_eq!(bubble_width_class(BubbleWidth::Full), "max-w-full");
+    }
+
+    #[test]
+    fn bubble_uses_the_chosen_width() {
+        let row = MessageRow {
+            width: BubbleWidth::Wide,
+            ..message_row(None, None)
+        };
+        let html = render(&view_message_row(&text_message("hi"), row, html! { "hi" }));
+        assert!(html.contains("class=\"bg-white p-3 rounded-xl shadow-sm max-w-3xl\""));
+    }
+
+    #[test]
+    fn repeat_badge_shows_the_multiplier() {
+        assert_eq!(render(&view_repeat_badge(0)), "");
+        assert!(render(&view_repeat_badge(1)).contains("×2"));
+        assert!(render(&view_repeat_badge(2)).contains("×3"));
+    }
+
+    #[test]
+    fn unknown_message_types_deserialize_as_unknown() {
+        let raw = r#"{"messageType":"typing","data":{"from":"bob"},"dataArray":null}"#;
+        let msg: WebSocketMessage = serde_json::from_str(raw).unwrap();
+        assert!(matches!(msg.message_type, MsgTypes::Unknown));
+    }
+
+    #[test]
+    fn unknown_frames_reach_the_custom_frame_callback() {
+        let raw = r#"{"messageType":"typing","data":{"from":"bob"},"dataArray":null}"#;
+        let received = Rc::new(RefCell::new(vec![]));
+        let callback = {
+            let received = Rc::clone(&received);
+            Some(Callback::from(move |frame: CustomFrame| {
+                received.borrow_mut().push(frame)
+            }))
+        };
+        assert!(dispatch_custom_frame(&callback, raw));
+        let received = received.borrow();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].message_type, "typing");
+        assert_eq!(received[0].frame["data"]["from"], "bob");
+    }
+
+    #[test]
+    fn unknown_frames_are_dropped_without_a_callback() {
+        assert!(!dispatch_custom_frame(&None, r#"{"messageType":"typing"}"#));
+    }
+
+    #[test]
+    fn frames_without_a_message_type_are_not_dispatched() {
+        let received = Rc::new(RefCell::new(0));
+        let callback = {
+            let received = Rc::clone(&received);
+            Some(Callback::from(move |_: CustomFrame| {
+                *received.borrow_mut() += 1
+            }))
+        };
+        assert!(!dispatch_custom_frame(&callback, r#"{"data":1}"#));
+        assert!(!dispatch_custom_frame(&callback, "not json"));
+        assert_eq!(*received.borrow(), 0);
+    }
+
+    fn shrug() -> RenderTransform {
+        RenderTransform::new(|m| {
+            (m.message == "/shrug").then(|| html! { <span>{"¯\\_(ツ)_/¯"}</span> })
+        })
+    }
+
+    #[test]
+    fn transform_overrides_matching_messages() {
+        let body = apply_render_transforms(&[shrug()], &text_message("/shrug"));
+        assert_eq!(render(&body.unwrap()), "<span>¯\\_(ツ)_/¯</span>");
+    }
+
+    #[test]
+    fn default_rendering_applies_when_no_transform_matches() {
+        assert!(apply_render_transforms(&[shrug()], &text_message("hello")).is_none());
+        assert!(apply_render_transforms(&[], &text_message("/shrug")).is_none());
+    }
+
+    #[test]
+    fn first_matching_transform_wins() {
+        let loud = RenderTransform::new(|m| Some(html! { <b>{m.message.to_uppercase()}</b> }));
+        let body = apply_render_transforms(&[shrug(), loud.clone()], &text_message("/shrug"));
+        assert!(render(&body.unwrap()).starts_with("<span>"));
+        let body = apply_render_transforms(&[loud, shrug()], &text_message("/shrug"));
+        assert_eq!(render(&body.unwrap()), "<b>/SHRUG</b>");
+    }
+
+    #[test]
+    fn code_mode_wraps_the_message_in_a_fence() {
+        assert_eq!(
+            outgoing_text("fn main() {}", true).as_deref(),
+            Some("```\nfn main() {}\n```")
+        );
+        assert_eq!(outgoing_text("hello", false).as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn input_is_cleared_after_send_unless_kept() {
+        assert_eq!(after_send(&Settings::default(), true), AfterSend::Clear);
+        let keep = Settings {
+            keep_input_after_send: true,
+            ..Settings::default()
+        };
+        assert_eq!(after_send(&keep, true), AfterSend::SelectAll);
+    }
+
+    #[test]
+    fn failed_writes_leave_the_text_in_place() {
+        assert_eq!(after_send(&Settings::default(), false), AfterSend::Keep);
+        let keep = Settings {
+            keep_input_after_send: true,
+            ..Settings::default()
+        };
+        assert_eq!(after_send(&keep, false), AfterSend::Keep);
+    }
+
+    #[test]
+    fn blank_messages_are_not_sent() {
+        for code_mode in [false, true] {
+            assert_eq!(outgoing_text("", code_mode), None);
+            assert_eq!(outgoing_text("  \t ", code_mode), None);
+        }
+    }
+
+    #[test]
+    fn end_jumps_to_bottom_of_message_list() {
+        assert_eq!(message_list_shortcut("End"), Some(Shortcut::JumpToBottom));
+    }
+
+    #[test]
+    fn other_message_list_keys_are_ignored() {
+        for key in ["Home", "PageDown", "ArrowDown", "e", ""] {
+            assert_eq!(message_list_shortcut(key), None, "{}", key);
+        }
+    }
+
+    #[test]
+    fn global_shortcuts_need_ctrl_and_shift() {
+        assert_eq!(
+            global_shortcut("F", true, true),
+            Some(Shortcut::ToggleFocusMode)
+        );
+        assert_eq!(
+            global_shortcut("f", true, true),
+            Some(Shortcut::ToggleFocusMode)
+        );
+        assert_eq!(
+            global_shortcut("P", true, true),
+            Some(Shortcut::TogglePalette)
+        );
+        assert_eq!(global_shortcut("f", true, false), None);
+        assert_eq!(global_shortcut("f", false, true), None);
+        assert_eq!(global_shortcut("x", true, true), None);
+    }
+}
@@ -0,0 +1,91 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dir {
+    Ltr,
+    Rtl,
+    Auto,
+}
+
+impl Dir {
+    pub fn attr(self) -> &'static str {
+        match self {
+            Dir::Ltr => "ltr",
+            Dir::Rtl => "rtl",
+            Dir::Auto => "auto",
+        }
+    }
+}
+
+// Digits and combining marks inside the Hebrew and Arabic blocks are weak in
+// the bidi algorithm, so they must not decide the direction on their own.
+fn is_weak_in_rtl_block(c: u32) -> bool {
+    matches!(c,
+        // Hebrew points and cantillation marks.
+        0x0591..=0x05BD | 0x05BF | 0x05C1..=0x05C2 | 0x05C4..=0x05C5 | 0x05C7
+        // Arabic combining marks.
+        | 0x0610..=0x061A | 0x064B..=0x065F | 0x0670
+        | 0x06D6..=0x06DC | 0x06DF..=0x06E4 | 0x06E7..=0x06E8 | 0x06EA..=0x06ED
+        // Arabic-Indic and Extended Arabic-Indic digits.
+        | 0x0660..=0x0669 | 0x06F0..=0x06F9)
+}
+
+fn is_rtl_char(c: char) -> bool {
+    let c = c as u32;
+    matches!(c,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF)
+        && !is_weak_in_rtl_block(c)
+}
+
+// Direction of the first strongly directional character, like the Unicode
+// bidi "first strong" rule; digits, punctuation and emoji are neutral.
+pub fn detect_direction(text: &str) -> Dir {
+    text.chars()
+        .find_map(|c| {
+            if is_rtl_char(c) {
+                Some(Dir::Rtl)
+            } else if c.is_alphabetic() {
+                Some(Dir::Ltr)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(Dir::Auto)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rtl_leading_text_is_rtl() {
+        assert_eq!(detect_direction("שלום world"), Dir::Rtl);
+        assert_eq!(detect_direction("مرحبا بالعالم"), Dir::Rtl);
+        assert_eq!(detect_direction("  «שלום»"), Dir::Rtl);
+    }
+
+    #[test]
+    fn ltr_leading_text_is_ltr() {
+        assert_eq!(detect_direction("hello שלום"), Dir::Ltr);
+        assert_eq!(detect_direction("42 is مرحبا"), Dir::Ltr);
+    }
+
+    #[test]
+    fn neutral_text_is_auto() {
+        assert_eq!(detect_direction(""), Dir::Auto);
+        assert_eq!(detect_direction("123 !? 😀"), Dir::Auto);
+    }
+
+    #[test]
+    fn arabic_indic_digits_are_neutral() {
+        assert_eq!(detect_direction("١٢٣"), Dir::Auto);
+        assert_eq!(detect_direction("١٢٣ hello"), Dir::Ltr);
+        assert_eq!(detect_direction("۱۲۳ hello"), Dir::Ltr);
+    }
+
+    #[test]
+    fn combining_marks_are_neutral() {
+        assert_eq!(detect_direction("\u{05B8}abc"), Dir::Ltr);
+        assert_eq!(detect_direction("\u{064E}abc"), Dir::Ltr);
+        // Marks on a Hebrew letter keep it RTL.
+        assert_eq!(detect_direction("\u{05E9}\u{05C1}\u{05B8}"), Dir::Rtl);
+    }
+}
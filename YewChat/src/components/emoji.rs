@@ -0,0 +1,143 @@
+const SHORTCODES: &[(&str, &str)] = &[
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("grin", "😁"),
+    ("joy", "😂"),
+    ("rofl", "🤣"),
+    ("wink", "😉"),
+    ("blush", "😊"),
+    ("heart_eyes", "😍"),
+    ("kissing_heart", "😘"),
+    ("yum", "😋"),
+    ("stuck_out_tongue", "😛"),
+    ("sunglasses", "😎"),
+    ("thinking", "🤔"),
+    ("neutral_face", "😐"),
+    ("smirk", "😏"),
+    ("unamused", "😒"),
+    ("roll_eyes", "🙄"),
+    ("relieved", "😌"),
+    ("sleeping", "😴"),
+    ("sweat_smile", "😅"),
+    ("cry", "😢"),
+    ("sob", "😭"),
+    ("scream", "😱"),
+    ("angry", "😠"),
+    ("rage", "😡"),
+    ("skull", "💀"),
+    ("poop", "💩"),
+    ("clown", "🤡"),
+    ("ghost", "👻"),
+    ("robot", "🤖"),
+    ("crab", "🦀"),
+    ("wave", "👋"),
+    ("ok_hand", "👌"),
+    ("thumbsup", "👍"),
+    ("thumbsdown", "👎"),
+    ("clap", "👏"),
+    ("pray", "🙏"),
+    ("muscle", "💪"),
+    ("eyes", "👀"),
+    ("heart", "❤️"),
+    ("purple_heart", "💜"),
+    ("broken_heart", "💔"),
+    ("fire", "🔥"),
+    ("sparkles", "✨"),
+    ("star", "⭐"),
+    ("tada", "🎉"),
+    ("rocket", "🚀"),
+    ("100", "💯"),
+    ("check", "✅"),
+    ("x", "❌"),
+    ("warning", "⚠️"),
+    ("coffee", "☕"),
+    ("pizza", "🍕"),
+    ("beer", "🍺"),
+];
+
+const MAX_CANDIDATES: usize = 8;
+
+// The partial shortcode being typed at the end of `before_caret`, e.g. "smi"
+// for "hello :smi".
+pub fn shortcode_token(before_caret: &str) -> Option<&str> {
+    let colon = before_caret.rfind(':')?;
+    let token = &before_caret[colon + 1..];
+    let starts_word = before_caret[..colon]
+        .chars()
+        .next_back()
+        .is_none_or(char::is_whitespace);
+    let valid = !token.is_empty()
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-');
+    if starts_word && valid {
+        Some(token)
+    } else {
+        None
+    }
+}
+
+pub fn shortcode_candidates(partial: &str) -> Vec<(&'static str, &'static str)> {
+    let partial = partial.to_ascii_lowercase();
+    let prefixed = SHORTCODES
+        .iter()
+        .filter(|(code, _)| code.starts_with(&partial));
+    let contained = SHORTCODES
+        .iter()
+        .filter(|(code, _)| !code.starts_with(&partial) && code.contains(&partial));
+    prefixed
+        .chain(contained)
+        .take(MAX_CANDIDATES)
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_is_the_partial_shortcode_before_the_caret() {
+        assert_eq!(shortcode_token(":smi"), Some("smi"));
+        assert_eq!(shortcode_token("hello :thumbs"), Some("thumbs"));
+        assert_eq!(shortcode_token("ok :+1"), Some("+1"));
+    }
+
+    #[test]
+    fn no_token_without_a_word_starting_colon() {
+        assert_eq!(shortcode_token("hello"), None);
+        assert_eq!(shortcode_token("hello :"), None);
+        assert_eq!(shortcode_token("12:30"), None);
+        assert_eq!(shortcode_token("see http:"), None);
+        assert_eq!(shortcode_token(":smile and more"), None);
+    }
+
+    #[test]
+    fn prefix_matches_come_before_substring_matches() {
+        let codes: Vec<_> = shortcode_candidates("heart")
+            .into_iter()
+            .map(|(code, _)| code)
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                "heart_eyes",
+                "heart",
+                "kissing_heart",
+                "purple_heart",
+                "broken_heart"
+            ]
+        );
+    }
+
+    #[test]
+    fn matching_ignores_case() {
+        assert_eq!(shortcode_candidates("CRAB"), [("crab", "🦀")]);
+    }
+
+    #[test]
+    fn candidates_are_capped() {
+        assert_eq!(shortcode_candidates("s").len(), MAX_CANDIDATES);
+        assert!(shortcode_candidates("zzz").is_empty());
+    }
+}
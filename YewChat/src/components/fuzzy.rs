@@ -0,0 +1,76 @@
+const MATCH_SCORE: i32 = 10;
+const CONSECUTIVE_BONUS: i32 = 15;
+const WORD_START_BONUS: i32 = 10;
+const GAP_PENALTY: i32 = 1;
+
+// Scores `candidate` against `query` as a case-insensitive subsequence match,
+// or returns None when some query character can't be found in order. Higher
+// is better: consecutive runs and matches at word starts are rewarded, skipped
+// characters cost a little.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let offset = candidate[position..]
+            .iter()
+            .position(|c| c.to_lowercase().eq(q.to_lowercase()))?;
+        let index = position + offset;
+        score += MATCH_SCORE;
+        if previous_match.is_some_and(|previous| previous + 1 == index) {
+            score += CONSECUTIVE_BONUS;
+        }
+        if index == 0 || !candidate[index - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        if previous_match.is_some() {
+            score -= GAP_PENALTY * offset as i32;
+        }
+        previous_match = Some(index);
+        position = index + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_subsequences_case_insensitively() {
+        assert!(fuzzy_score("ost", "Open settings").is_some());
+        assert!(fuzzy_score("OPSET", "open settings").is_some());
+        assert!(fuzzy_score("", "anything").is_some());
+    }
+
+    #[test]
+    fn rejects_out_of_order_or_missing_characters() {
+        assert_eq!(fuzzy_score("tso", "Open settings"), None);
+        assert_eq!(fuzzy_score("x", "Open settings"), None);
+        assert_eq!(fuzzy_score("settingss", "Open settings"), None);
+    }
+
+    #[test]
+    fn whitespace_in_the_query_is_ignored() {
+        assert_eq!(
+            fuzzy_score("open set", "Open settings"),
+            fuzzy_score("openset", "Open settings")
+        );
+    }
+
+    #[test]
+    fn consecutive_runs_beat_scattered_matches() {
+        assert!(fuzzy_score("set", "settings") > fuzzy_score("set", "s_e_t"));
+    }
+
+    #[test]
+    fn word_starts_beat_mid_word_matches() {
+        assert!(fuzzy_score("cm", "code mode") > fuzzy_score("cm", "income"));
+    }
+
+    #[test]
+    fn gaps_cost_points() {
+        assert!(fuzzy_score("ab", "a-b") > fuzzy_score("ab", "a---b"));
+    }
+}
@@ -0,0 +1,123 @@
+const PAIRED_MARKERS: &[&str] = &["**", "__", "~~", "*", "_", "`"];
+
+fn is_boundary(c: Option<char>) -> bool {
+    c.is_none_or(|c| !c.is_alphanumeric())
+}
+
+// Removes balanced `marker` pairs, keeping the text between them. Markers
+// must open and close at word boundaries so snake_case or 2*3*4 survive, and
+// unbalanced markers are left as typed.
+fn strip_pairs(text: &str, marker: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut previous: Option<char> = None;
+    while let Some(open) = rest.find(marker) {
+        let before = rest[..open].chars().next_back().or(previous);
+        let inner_start = open + marker.len();
+        let close = rest[inner_start..].find(marker).map(|i| inner_start + i);
+        match close {
+            Some(close)
+                if close > inner_start
+                    && is_boundary(before)
+                    && is_boundary(rest[close + marker.len()..].chars().next()) =>
+            {
+                out.push_str(&rest[..open]);
+                out.push_str(&rest[inner_start..close]);
+                previous = rest[..close].chars().next_back();
+                rest = &rest[close + marker.len()..];
+            }
+            _ => {
+                out.push_str(&rest[..inner_start]);
+                previous = marker.chars().next_back();
+                rest = &rest[inner_start..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Turns `[text](url)` into `text`.
+fn strip_links(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        let link = rest[open..].find("](").and_then(|mid| {
+            let close = rest[open + mid..].find(')')?;
+            Some((open + mid, open + mid + close))
+        });
+        match link {
+            Some((mid, close)) if !rest[open + 1..mid].contains('[') => {
+                out.push_str(&rest[..open]);
+                out.push_str(&rest[open + 1..mid]);
+                rest = &rest[close + 1..];
+            }
+            _ => {
+                out.push_str(&rest[..=open]);
+                rest = &rest[open + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn to_plaintext(markdown: &str) -> String {
+    let without_fences = markdown
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    PAIRED_MARKERS
+        .iter()
+        .fold(strip_links(&without_fences), |text, marker| {
+            strip_pairs(&text, marker)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_emphasis_markers() {
+        assert_eq!(to_plaintext("**bold** and __bold__"), "bold and bold");
+        assert_eq!(to_plaintext("*italic* and _italic_"), "italic and italic");
+        assert_eq!(to_plaintext("~~struck~~"), "struck");
+    }
+
+    #[test]
+    fn strips_inline_code_and_fences() {
+        assert_eq!(to_plaintext("run `cargo test` now"), "run cargo test now");
+        assert_eq!(to_plaintext("```rust\nfn main() {}\n```"), "fn main() {}");
+    }
+
+    #[test]
+    fn keeps_link_text_only() {
+        assert_eq!(
+            to_plaintext("see [the docs](https://yew.rs) first"),
+            "see the docs first"
+        );
+        assert_eq!(to_plaintext("[not a link] (x)"), "[not a link] (x)");
+    }
+
+    #[test]
+    fn markers_inside_words_are_left_alone() {
+        assert_eq!(to_plaintext("snake_case_name"), "snake_case_name");
+        assert_eq!(to_plaintext("2*3*4"), "2*3*4");
+    }
+
+    #[test]
+    fn nested_markers_are_all_stripped() {
+        assert_eq!(to_plaintext("**bold _and italic_**"), "bold and italic");
+        assert_eq!(to_plaintext("[**loud** link](https://a.b)"), "loud link");
+    }
+
+    #[test]
+    fn unbalanced_markers_are_kept_as_typed() {
+        assert_eq!(to_plaintext("**not closed"), "**not closed");
+        assert_eq!(to_plaintext("a * b"), "a * b");
+        assert_eq!(to_plaintext("`open"), "`open");
+        assert_eq!(to_plaintext("****"), "****");
+    }
+}
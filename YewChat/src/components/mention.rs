@@ -0,0 +1,125 @@
+use yew::prelude::*;
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn prefix_len_ignore_case(text: &str, prefix: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    let mut len = 0;
+    for p in prefix.chars() {
+        let (offset, t) = chars.next()?;
+        if !t.to_lowercase().eq(p.to_lowercase()) {
+            return None;
+        }
+        len = offset + t.len_utf8();
+    }
+    Some(len)
+}
+
+pub fn name_matches(text: &str, name: &str) -> Vec<(usize, usize)> {
+    let mut matches: Vec<(usize, usize)> = vec![];
+    if name.is_empty() {
+        return matches;
+    }
+    let mut previous: Option<char> = None;
+    for (start, c) in text.char_indices() {
+        let at_word_start = !previous.is_some_and(is_word_char);
+        previous = Some(c);
+        if !at_word_start || matches.last().is_some_and(|&(_, end)| start < end) {
+            continue;
+        }
+        if let Some(end) = prefix_len_ignore_case(&text[start..], name).map(|len| start + len) {
+            if !text[end..].chars().next().is_some_and(is_word_char) {
+                matches.push((start, end));
+            }
+        }
+    }
+    matches
+}
+
+// What the Mention action inserts into the composer.
+pub fn mention_text(name: &str) -> String {
+    format!("@{} ", name)
+}
+
+pub fn highlight_own_name(text: &str, name: &str) -> Html {
+    let mut last = 0;
+    let mut parts = vec![];
+    for (start, end) in name_matches(text, name) {
+        parts.push(html! { {&text[last..start]} });
+        parts.push(html! { <mark class="bg-yellow-200 rounded px-0.5">{&text[start..end]}</mark> });
+        last = end;
+    }
+    parts.push(html! { {&text[last..]} });
+    parts.into_iter().collect::<Html>()
+}
+
+#[cfg(test)]
+mod tests {
+    use yew::virtual_dom::VNode;
+
+    use super::*;
+    use crate::components::utf16::splice_utf16;
+
+    fn marked(node: &Html) -> Vec<String> {
+        match node {
+            VNode::VList(list) => list.iter().flat_map(marked).collect(),
+            VNode::VTag(tag) if tag.tag() == "mark" => tag
+                .children()
+                .iter()
+                .filter_map(|child| match child {
+                    VNode::VText(text) => Some(text.text.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn matches_whole_words_only() {
+        assert_eq!(name_matches("hi Al!", "Al"), vec![(3, 5)]);
+        assert!(name_matches("hi Alice", "Al").is_empty());
+        assert!(name_matches("Hal", "Al").is_empty());
+        assert!(name_matches("Al_x", "Al").is_empty());
+    }
+
+    #[test]
+    fn matching_ignores_case_and_finds_every_occurrence() {
+        assert_eq!(
+            name_matches("al, AL and Al", "Al"),
+            vec![(0, 2), (4, 6), (11, 13)]
+        );
+        assert!(name_matches("anything", "").is_empty());
+    }
+
+    #[test]
+    fn highlight_marks_only_the_name() {
+        assert_eq!(
+            marked(&highlight_own_name("Alice, ask Al", "Al")),
+            vec!["Al"]
+        );
+        assert_eq!(
+            marked(&highlight_own_name("alice alice", "alice")),
+            vec!["alice", "alice"]
+        );
+        assert!(marked(&highlight_own_name("Alice", "Al")).is_empty());
+    }
+
+    #[test]
+    fn mention_is_inserted_at_the_cursor() {
+        assert_eq!(
+            splice_utf16("hi ", 3, 3, &mention_text("bob")),
+            ("hi @bob ".to_string(), 8)
+        );
+        assert_eq!(
+            splice_utf16("hi  there", 3, 3, &mention_text("bob")),
+            ("hi @bob  there".to_string(), 8)
+        );
+        assert_eq!(
+            splice_utf16("hi Al!", 3, 5, &mention_text("alice")),
+            ("hi @alice !".to_string(), 10)
+        );
+    }
+}
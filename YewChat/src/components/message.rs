@@ -0,0 +1,196 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Deserialize)]
+pub struct MessageData {
+    pub from: String,
+    pub message: String,
+    #[serde(default)]
+    pub time: Option<f64>,
+    #[serde(skip)]
+    pub(crate) repeats: u32,
+}
+
+const REPEAT_WINDOW_MS: f64 = 2_000.0;
+
+// An accidental double-send: same author and text, arriving within
+// REPEAT_WINDOW_MS of the bubble it would merge into.
+pub fn is_repeat(previous: &MessageData, next: &MessageData) -> bool {
+    match (previous.time, next.time) {
+        (Some(previous_time), Some(next_time)) => {
+            previous.from == next.from
+                && previous.message == next.message
+                && (0.0..=REPEAT_WINDOW_MS).contains(&(next_time - previous_time))
+        }
+        _ => false,
+    }
+}
+
+// Appends `message`, or with `merge_repeats` on, bumps the last bubble's
+// multiplier instead when it is a repeat of it. The bubble takes the repeat's
+// time, so each send is compared with the one just before it.
+pub fn push_message(messages: &mut Vec<MessageData>, message: MessageData, merge_repeats: bool) {
+    if merge_repeats {
+        if let Some(last) = messages.last_mut() {
+            if is_repeat(last, &message) {
+                last.repeats += 1;
+                last.time = message.time;
+                return;
+            }
+        }
+    }
+    messages.push(message);
+}
+
+pub fn parse_message_data(
+    data: Option<Value>,
+    data_array: Option<Vec<Value>>,
+) -> Option<MessageData> {
+    let data = data.or_else(|| data_array?.into_iter().next());
+    match data? {
+        Value::String(s) => serde_json::from_str(&s).ok(),
+        v @ Value::Object(_) => serde_json::from_value(v).ok(),
+        _ => None,
+    }
+}
+
+pub fn user_display_name(entry: &Value) -> Option<String> {
+    let name = match entry {
+        Value::String(s) => s.trim().to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Object(map) => ["name", "nick", "username"]
+            .iter()
+            .find_map(|key| map.get(*key).and_then(Value::as_str))?
+            .trim()
+            .to_string(),
+        _ => return None,
+    };
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_stringified_message_data() {
+        let data = json!(r#"{"from":"alice","message":"hi","time":1.5}"#);
+        let m = parse_message_data(Some(data), None).unwrap();
+        assert_eq!(m.from, "alice");
+        assert_eq!(m.message, "hi");
+        assert_eq!(m.time, Some(1.5));
+    }
+
+    #[test]
+    fn parses_message_data_sent_as_an_object() {
+        let data = json!({"from": "bob", "message": "hey"});
+        let m = parse_message_data(Some(data), None).unwrap();
+        assert_eq!(m.from, "bob");
+        assert_eq!(m.message, "hey");
+        assert_eq!(m.time, None);
+    }
+
+    #[test]
+    fn falls_back_to_the_first_data_array_entry() {
+        let entry = json!({"from": "carol", "message": "yo"});
+        let m = parse_message_data(None, Some(vec![entry])).unwrap();
+        assert_eq!(m.from, "carol");
+    }
+
+    #[test]
+    fn mismatched_data_is_rejected_without_panicking() {
+        assert!(parse_message_data(Some(json!(42)), None).is_none());
+        assert!(parse_message_data(Some(json!("not json")), None).is_none());
+        assert!(parse_message_data(Some(json!({"from": "dave"})), None).is_none());
+        assert!(parse_message_data(Some(json!(r#"{"text":"hi"}"#)), None).is_none());
+        assert!(parse_message_data(None, Some(vec![])).is_none());
+        assert!(parse_message_data(None, None).is_none());
+    }
+
+    #[test]
+    fn mixed_type_user_list_is_coerced_to_names() {
+        let entries = vec![
+            json!("alice"),
+            json!(42),
+            json!({"name": "bob"}),
+            json!({"nick": " carol "}),
+            json!({"username": "dave"}),
+            json!({"id": 7}),
+            json!(null),
+            json!(true),
+            json!(["erin"]),
+            json!("   "),
+        ];
+        let names: Vec<String> = entries.iter().filter_map(user_display_name).collect();
+        assert_eq!(names, vec!["alice", "42", "bob", "carol", "dave"]);
+    }
+
+    fn message(from: &str, text: &str, time: Option<f64>) -> MessageData {
+        MessageData {
+            from: from.to_string(),
+            message: text.to_string(),
+            time,
+            repeats: 0,
+        }
+    }
+
+    #[test]
+    fn same_author_and_text_within_the_window_is_a_repeat() {
+        let first = message("alice", "hi", Some(1_000.0));
+        assert!(is_repeat(&first, &message("alice", "hi", Some(1_000.0))));
+        assert!(is_repeat(&first, &message("alice", "hi", Some(3_000.0))));
+    }
+
+    #[test]
+    fn late_or_different_messages_are_not_repeats() {
+        let first = message("alice", "hi", Some(1_000.0));
+        assert!(!is_repeat(&first, &message("alice", "hi", Some(3_001.0))));
+        assert!(!is_repeat(&first, &message("alice", "hi", Some(999.0))));
+        assert!(!is_repeat(&first, &message("bob", "hi", Some(1_500.0))));
+        assert!(!is_repeat(&first, &message("alice", "hi!", Some(1_500.0))));
+        assert!(!is_repeat(&first, &message("alice", "hi", None)));
+    }
+
+    #[test]
+    fn repeats_bump_the_multiplier_when_merging() {
+        let mut messages = vec![];
+        push_message(&mut messages, message("alice", "hi", Some(0.0)), true);
+        push_message(&mut messages, message("alice", "hi", Some(500.0)), true);
+        push_message(&mut messages, message("alice", "hi", Some(900.0)), true);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].repeats, 2);
+
+        push_message(&mut messages, message("bob", "hi", Some(1_000.0)), true);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].repeats, 0);
+    }
+
+    #[test]
+    fn each_repeat_is_timed_against_the_previous_send() {
+        let mut messages = vec![];
+        push_message(&mut messages, message("alice", "hi", Some(0.0)), true);
+        push_message(&mut messages, message("alice", "hi", Some(1_500.0)), true);
+        push_message(&mut messages, message("alice", "hi", Some(3_000.0)), true);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].repeats, 2);
+        assert_eq!(messages[0].time, Some(3_000.0));
+
+        push_message(&mut messages, message("alice", "hi", Some(5_001.0)), true);
+        assert_eq!(messages.len(), 2);
+    }
+
+    #[test]
+    fn repeats_stay_separate_when_merging_is_off() {
+        let mut messages = vec![];
+        push_message(&mut messages, message("alice", "hi", Some(0.0)), false);
+        push_message(&mut messages, message("alice", "hi", Some(500.0)), false);
+        assert_eq!(messages.len(), 2);
+        assert!(messages.iter().all(|m| m.repeats == 0));
+    }
+}
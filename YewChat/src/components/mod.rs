@@ -1,2 +1,13 @@
+pub mod avatar;
 pub mod chat;
-pub mod login;
\ No newline at end of file
+pub mod direction;
+pub mod emoji;
+pub mod fuzzy;
+pub mod login;
+pub mod markdown;
+pub mod mention;
+pub mod message;
+pub mod truncate;
+pub mod utf16;
+pub mod version;
+pub mod viewport;
\ No newline at end of file
@@ -0,0 +1,53 @@
+// Long enough that real URLs, hashes and paths are never cut; only blobs like
+// pasted base64 reach it.
+pub const MAX_TOKEN_CHARS: usize = 500;
+
+// Cuts every whitespace-free run longer than `max_chars` down to `max_chars`
+// plus an ellipsis, so one huge blob can't widen the bubble. Returns None
+// when nothing needed cutting.
+pub fn truncate_long_tokens(text: &str, max_chars: usize) -> Option<String> {
+    let mut truncated = false;
+    let out = text
+        .split_inclusive(char::is_whitespace)
+        .map(|piece| {
+            let token = piece.trim_end_matches(char::is_whitespace);
+            match token.char_indices().nth(max_chars) {
+                Some((cut, _)) => {
+                    truncated = true;
+                    format!("{}…{}", &token[..cut], &piece[token.len()..])
+                }
+                None => piece.to_string(),
+            }
+        })
+        .collect();
+    truncated.then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn over_length_tokens_are_cut_with_an_ellipsis() {
+        let blob = "A".repeat(MAX_TOKEN_CHARS + 100);
+        let text = format!("look: {} ok", blob);
+        let expected = format!("look: {}… ok", "A".repeat(MAX_TOKEN_CHARS));
+        assert_eq!(truncate_long_tokens(&text, MAX_TOKEN_CHARS), Some(expected));
+    }
+
+    #[test]
+    fn tokens_up_to_the_cap_are_untouched() {
+        let url = format!("https://example.com/{}", "a".repeat(200));
+        assert_eq!(truncate_long_tokens(&url, MAX_TOKEN_CHARS), None);
+        let exact = "b".repeat(MAX_TOKEN_CHARS);
+        assert_eq!(truncate_long_tokens(&exact, MAX_TOKEN_CHARS), None);
+    }
+
+    #[test]
+    fn cuts_count_characters_and_keep_whitespace() {
+        assert_eq!(
+            truncate_long_tokens("ééééé\nxy  zzzz", 3),
+            Some("ééé…\nxy  zzz…".to_string())
+        );
+    }
+}
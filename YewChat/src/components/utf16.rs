@@ -0,0 +1,113 @@
+// How many UTF-16 units of a paste fit, given the input's current length and
+// the length of the selection the paste replaces.
+pub fn paste_allowance(current_len: u32, selected_len: u32, max_len: u32) -> u32 {
+    max_len.saturating_sub(current_len.saturating_sub(selected_len))
+}
+
+pub fn truncate_utf16(s: &str, max_units: u32) -> &str {
+    let mut units = 0;
+    for (i, c) in s.char_indices() {
+        units += c.len_utf16() as u32;
+        if units > max_units {
+            return &s[..i];
+        }
+    }
+    s
+}
+
+pub fn utf16_to_byte_index(s: &str, utf16_index: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in s.char_indices() {
+        if units >= utf16_index {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    s.len()
+}
+
+// Replaces the UTF-16 range `start..end` of `value` (as reported by
+// selectionStart/selectionEnd) with `text`, returning the new value and the
+// caret position just after the inserted text.
+pub fn splice_utf16(value: &str, start: u32, end: u32, text: &str) -> (String, u32) {
+    let (start, end) = (start.min(end), start.max(end));
+    let start_byte = utf16_to_byte_index(value, start as usize);
+    let end_byte = utf16_to_byte_index(value, end as usize);
+    let caret = value[..start_byte].encode_utf16().count() + text.encode_utf16().count();
+    let spliced = format!("{}{}{}", &value[..start_byte], text, &value[end_byte..]);
+    (spliced, caret as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allowance_counts_the_replaced_selection_as_free() {
+        assert_eq!(paste_allowance(0, 0, 1000), 1000);
+        assert_eq!(paste_allowance(990, 0, 1000), 10);
+        assert_eq!(paste_allowance(990, 40, 1000), 50);
+        assert_eq!(paste_allowance(1000, 0, 1000), 0);
+    }
+
+    #[test]
+    fn allowance_never_underflows() {
+        assert_eq!(paste_allowance(1200, 0, 1000), 0);
+        assert_eq!(paste_allowance(10, 20, 1000), 1000);
+    }
+
+    #[test]
+    fn truncates_to_the_utf16_limit() {
+        assert_eq!(truncate_utf16("hello", 3), "hel");
+        assert_eq!(truncate_utf16("hello", 5), "hello");
+        assert_eq!(truncate_utf16("hello", 50), "hello");
+        assert_eq!(truncate_utf16("hello", 0), "");
+    }
+
+    #[test]
+    fn truncation_does_not_split_surrogate_pairs() {
+        // Each emoji is two UTF-16 units.
+        assert_eq!(truncate_utf16("😀😀", 3), "😀");
+        assert_eq!(truncate_utf16("😀😀", 1), "");
+        assert_eq!(truncate_utf16("é😀", 2), "é");
+    }
+
+    #[test]
+    fn splices_in_the_middle() {
+        assert_eq!(
+            splice_utf16("hello world", 5, 5, ","),
+            ("hello, world".to_string(), 6)
+        );
+    }
+
+    #[test]
+    fn splices_at_the_end() {
+        assert_eq!(splice_utf16("hi", 2, 2, " 👋"), ("hi 👋".to_string(), 5));
+        // A cursor past the end clamps to the end.
+        assert_eq!(splice_utf16("hi", 9, 9, "!"), ("hi!".to_string(), 3));
+    }
+
+    #[test]
+    fn splices_at_the_start() {
+        assert_eq!(
+            splice_utf16("reply", 0, 0, "> quote "),
+            ("> quote reply".to_string(), 8)
+        );
+    }
+
+    #[test]
+    fn replaces_the_selection() {
+        assert_eq!(
+            splice_utf16("I :smi: you", 2, 7, "😄"),
+            ("I 😄 you".to_string(), 4)
+        );
+        // selectionStart/End can arrive reversed.
+        assert_eq!(splice_utf16("abcdef", 4, 1, "X"), ("aXef".to_string(), 2));
+    }
+
+    #[test]
+    fn offsets_are_utf16_units_not_bytes() {
+        // "é" is two bytes but one unit; "😀" is four bytes but two units.
+        assert_eq!(splice_utf16("é😀z", 3, 3, "y"), ("é😀yz".to_string(), 4));
+    }
+}
@@ -0,0 +1,67 @@
+pub const CLIENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn version_parts(version: &str) -> Vec<u64> {
+    let version = version.trim().trim_start_matches('v');
+    let release = version.split(['-', '+']).next().unwrap_or(version);
+    release
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+pub fn version_is_newer(server: &str, client: &str) -> bool {
+    let server = version_parts(server);
+    let client = version_parts(client);
+    let len = server.len().max(client.len());
+    let pad = |v: &[u64], i: usize| v.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| pad(&server, i).cmp(&pad(&client, i)))
+        .find(|ordering| ordering.is_ne())
+        .is_some_and(|ordering| ordering.is_gt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn higher_component_is_newer() {
+        assert!(version_is_newer("0.1.1", "0.1.0"));
+        assert!(version_is_newer("0.2.0", "0.1.9"));
+        assert!(version_is_newer("1.0.0", "0.9.9"));
+    }
+
+    #[test]
+    fn same_or_lower_is_not_newer() {
+        assert!(!version_is_newer("0.1.0", "0.1.0"));
+        assert!(!version_is_newer("0.1.0", "0.1.1"));
+        assert!(!version_is_newer("0.9.9", "1.0.0"));
+    }
+
+    #[test]
+    fn components_compare_numerically() {
+        assert!(version_is_newer("0.10.0", "0.9.0"));
+        assert!(!version_is_newer("0.9.0", "0.10.0"));
+    }
+
+    #[test]
+    fn missing_components_count_as_zero() {
+        assert!(!version_is_newer("1.0", "1.0.0"));
+        assert!(version_is_newer("1.0.1", "1.0"));
+        assert!(version_is_newer("2", "1.9.9"));
+    }
+
+    #[test]
+    fn prefix_and_suffixes_are_ignored() {
+        assert!(version_is_newer("v0.2.0", "0.1.0"));
+        assert!(!version_is_newer("0.1.0-beta.2", "0.1.0"));
+        assert!(!version_is_newer("0.1.0+build.7", "0.1.0"));
+        assert!(version_is_newer(" 0.1.1 ", "0.1.0"));
+    }
+
+    #[test]
+    fn garbage_is_not_newer_than_a_release() {
+        assert!(!version_is_newer("", CLIENT_VERSION));
+        assert!(!version_is_newer("latest", CLIENT_VERSION));
+    }
+}
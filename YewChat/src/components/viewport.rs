@@ -0,0 +1,60 @@
+use wasm_bindgen::JsCast;
+use web_sys::EventTarget;
+
+// How far the on-screen keyboard covers the bottom of the layout viewport.
+fn keyboard_offset(layout_height: f64, viewport_height: f64, viewport_offset_top: f64) -> f64 {
+    (layout_height - viewport_height - viewport_offset_top).max(0.0)
+}
+
+pub fn visual_viewport() -> Option<EventTarget> {
+    let window = web_sys::window()?;
+    js_sys::Reflect::get(&window, &"visualViewport".into())
+        .ok()?
+        .dyn_into::<EventTarget>()
+        .ok()
+}
+
+pub fn current_keyboard_offset() -> f64 {
+    let (window, viewport) = match (web_sys::window(), visual_viewport()) {
+        (Some(window), Some(viewport)) => (window, viewport),
+        _ => return 0.0,
+    };
+    let number = |target: &wasm_bindgen::JsValue, key: &str| {
+        js_sys::Reflect::get(target, &key.into())
+            .ok()
+            .and_then(|v| v.as_f64())
+    };
+    match (
+        number(&window, "innerHeight"),
+        number(&viewport, "height"),
+        number(&viewport, "offsetTop"),
+    ) {
+        (Some(layout), Some(height), Some(offset_top)) => {
+            keyboard_offset(layout, height, offset_top)
+        }
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_offset_without_a_keyboard() {
+        assert_eq!(keyboard_offset(800.0, 800.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn offset_is_the_covered_height() {
+        assert_eq!(keyboard_offset(800.0, 500.0, 0.0), 300.0);
+        // A scrolled visual viewport covers less of the layout's bottom.
+        assert_eq!(keyboard_offset(800.0, 500.0, 120.0), 180.0);
+    }
+
+    #[test]
+    fn offset_never_goes_negative() {
+        assert_eq!(keyboard_offset(800.0, 820.0, 0.0), 0.0);
+        assert_eq!(keyboard_offset(800.0, 500.0, 400.0), 0.0);
+    }
+}
@@ -11,9 +11,12 @@ use yew::functional::*;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
-use components::chat::Chat;
 use components::login::Login;
 
+pub use components::chat::{Chat, ChatProps, CustomFrame, RenderTransform};
+pub use components::message::MessageData;
+pub use services::websocket::{ConnectionListener, ConnectionState};
+
 // When the `wee_alloc` feature is enabled, this uses `wee_alloc` as the global
 // allocator.
 //
@@ -40,36 +43,50 @@ pub struct UserInner {
     pub username: RefCell<String>,
 }
 
+#[derive(Properties, PartialEq, Default)]
+struct MainProps {
+    #[prop_or_default]
+    chat: ChatProps,
+}
+
 #[function_component(Main)]
-fn main() -> Html {
+fn main(props: &MainProps) -> Html {
     let ctx = use_state(|| {
         Rc::new(UserInner {
             username: RefCell::new("initial".into()),
         })
     });
+    let chat = props.chat.clone();
 
     html! {
         <ContextProvider<User> context={(*ctx).clone()}>
             <BrowserRouter>
                 <div class="flex w-screen h-screen">
-                    <Switch<Route> render={Switch::render(switch)}/>
+                    <Switch<Route> render={Switch::render(move |route| switch(route, &chat))}/>
                 </div>
             </BrowserRouter>
         </ContextProvider<User>>
     }
 }
 
-fn switch(selected_route: &Route) -> Html {
+fn switch(selected_route: &Route, chat: &ChatProps) -> Html {
     match selected_route {
         Route::Login => html! {<Login />},
-        Route::Chat => html! {<Chat/>},
+        Route::Chat => html! {<Chat ..chat.clone()/>},
         Route::NotFound => html! {<h1>{"404 baby"}</h1>},
     }
 }
 
 #[wasm_bindgen]
 pub fn run_app() -> Result<(), JsValue> {
-    wasm_logger::init(wasm_logger::Config::default());
-    yew::start_app::<Main>();
+    run_app_with(ChatProps::default());
     Ok(())
+}
+
+// Starts the app with the given props for the chat page, for crates that
+// depend on this one to listen for connection changes, handle custom frames
+// or override message rendering.
+pub fn run_app_with(chat: ChatProps) {
+    wasm_logger::init(wasm_logger::Config::default());
+    yew::start_app_with_props::<Main>(MainProps { chat });
 }
\ No newline at end of file
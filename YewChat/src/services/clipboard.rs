@@ -0,0 +1,23 @@
+use wasm_bindgen::{JsCast, JsValue};
+
+pub fn copy_to_clipboard(text: &str) {
+    let window = match web_sys::window() {
+        Some(window) => window,
+        None => return,
+    };
+    let clipboard = match js_sys::Reflect::get(&window.navigator(), &"clipboard".into()) {
+        Ok(clipboard) if !clipboard.is_undefined() => clipboard,
+        _ => {
+            log::error!("clipboard API unavailable");
+            return;
+        }
+    };
+    let write_text = js_sys::Reflect::get(&clipboard, &"writeText".into())
+        .ok()
+        .and_then(|f| f.dyn_into::<js_sys::Function>().ok());
+    if let Some(write_text) = write_text {
+        if let Err(e) = write_text.call1(&clipboard, &JsValue::from_str(text)) {
+            log::error!("clipboard: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use serde_json::Value;
+
+const REDACTED_KEYS: &[&str] = &["token", "authtoken", "auth_token", "password", "secret"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameDirection {
+    Inbound,
+    Outbound,
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if REDACTED_KEYS.contains(&key.to_ascii_lowercase().as_str()) {
+                    *v = Value::String("[redacted]".into());
+                } else {
+                    redact(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        Value::String(s) => {
+            // Message payloads are often JSON nested inside a string.
+            if let Ok(mut nested @ Value::Object(_)) = serde_json::from_str::<Value>(s) {
+                redact(&mut nested);
+                *s = nested.to_string();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn format_frame(raw: &str) -> String {
+    match serde_json::from_str::<Value>(raw) {
+        Ok(mut value) => {
+            redact(&mut value);
+            serde_json::to_string_pretty(&value).unwrap_or_else(|_| raw.to_string())
+        }
+        Err(_) => raw.to_string(),
+    }
+}
+
+// Logs a frame with secrets redacted, but only while the debug toggle is on.
+pub fn log_frame(enabled: bool, direction: FrameDirection, raw: &str) {
+    log_frame_with(enabled, direction, raw, |line| log::info!("{}", line));
+}
+
+fn log_frame_with(enabled: bool, direction: FrameDirection, raw: &str, sink: impl FnOnce(String)) {
+    if !enabled {
+        return;
+    }
+    let arrow = match direction {
+        FrameDirection::Inbound => "⬇",
+        FrameDirection::Outbound => "⬆",
+    };
+    sink(format!(
+        "{} {:?} frame\n{}",
+        arrow,
+        direction,
+        format_frame(raw)
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logged(enabled: bool, raw: &str) -> Vec<String> {
+        let mut lines = vec![];
+        log_frame_with(enabled, FrameDirection::Inbound, raw, |line| {
+            lines.push(line)
+        });
+        lines
+    }
+
+    #[test]
+    fn nothing_is_logged_while_the_toggle_is_off() {
+        assert!(logged(false, r#"{"messageType":"users"}"#).is_empty());
+    }
+
+    #[test]
+    fn frames_are_logged_while_the_toggle_is_on() {
+        let lines = logged(true, r#"{"messageType":"users"}"#);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("⬇ Inbound frame"));
+        assert!(lines[0].contains("users"));
+    }
+
+    #[test]
+    fn secrets_are_redacted_including_nested_payloads() {
+        let raw = r#"{"token":"abc123","data":"{\"from\":\"a\",\"Password\":\"hunter2\"}"}"#;
+        let lines = logged(true, raw);
+        assert!(!lines[0].contains("abc123"));
+        assert!(!lines[0].contains("hunter2"));
+        assert!(lines[0].contains("[redacted]"));
+    }
+}
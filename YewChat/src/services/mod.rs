@@ -1,2 +1,5 @@
+pub mod clipboard;
 pub mod event_bus;
+pub mod frame_log;
+pub mod settings;
 pub mod websocket;
\ No newline at end of file
@@ -0,0 +1,60 @@
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+const STORAGE_KEY: &str = "yewchat.settings";
+const LOCAL_AVATARS_BY_DEFAULT: bool = cfg!(feature = "local-avatars");
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum BubbleWidth {
+    Narrow,
+    #[default]
+    Medium,
+    Wide,
+    Full,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub collapse_images: bool,
+    pub expand_latest_image_only: bool,
+    pub keep_input_after_send: bool,
+    pub highlight_own_name: bool,
+    pub debug_frames: bool,
+    pub timestamp_gutter: bool,
+    pub focus_mode: bool,
+    pub local_avatars: bool,
+    pub bubble_width: BubbleWidth,
+    pub merge_repeats: bool,
+    pub dismissed_update_version: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            collapse_images: false,
+            expand_latest_image_only: false,
+            keep_input_after_send: false,
+            highlight_own_name: false,
+            debug_frames: false,
+            timestamp_gutter: false,
+            focus_mode: false,
+            local_avatars: LOCAL_AVATARS_BY_DEFAULT,
+            bubble_width: BubbleWidth::default(),
+            merge_repeats: false,
+            dismissed_update_version: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        LocalStorage::get(STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = LocalStorage::set(STORAGE_KEY, self) {
+            log::error!("failed to save settings: {:?}", e);
+        }
+    }
+}
@@ -1,42 +1,118 @@
-use futures::{channel::mpsc::Sender, SinkExt, StreamExt};
-use reqwasm::websocket::{futures::WebSocket, Message};
+use std::{cell::RefCell, rc::Rc};
+
+use futures::{
+    channel::{mpsc::Sender, oneshot},
+    future::{poll_fn, select, Either},
+    SinkExt, StreamExt,
+};
+use reqwasm::websocket::{futures::WebSocket, Message, State};
 
 use wasm_bindgen_futures::spawn_local;
+use yew::Callback;
 use yew_agent::Dispatched;
 
 use crate::services::event_bus::{EventBus, Request};
 
+pub struct OutgoingFrame {
+    pub text: String,
+    pub written: Option<oneshot::Sender<bool>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectionState {
+    Connected,
+    Disconnected,
+}
+
+pub type ConnectionListener = Callback<ConnectionState>;
+
+// A point in the socket's life, as seen by the writer and reader tasks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SocketEvent {
+    // The writer stopped waiting for the sink; `opened` is false when it gave
+    // up because the reader saw the connection close first.
+    SinkSettled { opened: bool },
+    // The read stream ended: the socket closed, cleanly or after an error.
+    StreamEnded,
+}
+
+fn state_change(event: SocketEvent) -> Option<ConnectionState> {
+    match event {
+        SocketEvent::SinkSettled { opened: true } => Some(ConnectionState::Connected),
+        // A socket that never opened reports Disconnected once its stream ends.
+        SocketEvent::SinkSettled { opened: false } => None,
+        SocketEvent::StreamEnded => Some(ConnectionState::Disconnected),
+    }
+}
+
+fn notify(listener: &Option<ConnectionListener>, event: SocketEvent) {
+    if let (Some(listener), Some(state)) = (listener, state_change(event)) {
+        listener.emit(state);
+    }
+}
+
 pub struct WebsocketService {
-    pub tx: Sender<String>,
+    pub tx: Sender<OutgoingFrame>,
 }
 
-impl WebsocketService {
-    pub fn new() -> Self {
-        let ws = WebSocket::open("ws://127.0.0.1:8080").unwrap();
+// Frames only reach the server while the socket is open; once it closes,
+// send_with_str no longer throws, so success has to be judged from the state.
+fn is_open(ws: &RefCell<WebSocket>) -> bool {
+    matches!(ws.borrow().state(), State::Open)
+}
 
-        let (mut write, mut read) = ws.split();
+impl WebsocketService {
+    pub fn new(listener: Option<ConnectionListener>) -> Self {
+        // Both tasks share the socket rather than splitting it, so the writer
+        // can check its state. Borrows never outlive a single poll.
+        let ws = Rc::new(RefCell::new(
+            WebSocket::open("ws://127.0.0.1:8080").unwrap(),
+        ));
+        let write = Rc::clone(&ws);
+        let read = ws;
 
-        let (in_tx, mut in_rx) = futures::channel::mpsc::channel::<String>(1000);
+        let (in_tx, mut in_rx) = futures::channel::mpsc::channel::<OutgoingFrame>(1000);
+        let (closed_tx, mut closed_rx) = oneshot::channel::<()>();
         let mut event_bus = EventBus::dispatcher();
 
+        let write_listener = listener.clone();
         spawn_local(async move {
-            while let Some(s) = in_rx.next().await {
-                log::debug!("got event from channel! {}", s);
-                write.send(Message::Text(s)).await.unwrap();
+            // The sink only wakes once the socket has opened, so a server that
+            // is never reached would leave this pending; the reader's close
+            // signal ends the wait instead.
+            let ready = poll_fn(|cx| write.borrow_mut().poll_ready_unpin(cx));
+            let opened = matches!(
+                select(ready, &mut closed_rx).await,
+                Either::Left((Ok(()), _))
+            ) && is_open(&write);
+            notify(&write_listener, SocketEvent::SinkSettled { opened });
+            while let Some(frame) = in_rx.next().await {
+                let sent = is_open(&write)
+                    && match write
+                        .borrow_mut()
+                        .start_send_unpin(Message::Text(frame.text))
+                    {
+                        Ok(()) => true,
+                        Err(e) => {
+                            log::error!("ws write: {:?}", e);
+                            false
+                        }
+                    };
+                if let Some(written) = frame.written {
+                    let _ = written.send(sent);
+                }
             }
         });
 
         spawn_local(async move {
-            while let Some(msg) = read.next().await {
+            while let Some(msg) = poll_fn(|cx| read.borrow_mut().poll_next_unpin(cx)).await {
                 match msg {
                     Ok(Message::Text(data)) => {
-                        log::debug!("from websocket: {}", data);
                         event_bus.send(Request::EventBusMsg(data));
                     }
                     Ok(Message::Bytes(b)) => {
                         let decoded = std::str::from_utf8(&b);
                         if let Ok(val) = decoded {
-                            log::debug!("from websocket: {}", val);
                             event_bus.send(Request::EventBusMsg(val.into()));
                         }
                     }
@@ -46,8 +122,50 @@ impl WebsocketService {
                 }
             }
             log::debug!("WebSocket Closed");
+            let _ = closed_tx.send(());
+            notify(&listener, SocketEvent::StreamEnded);
         });
 
         Self { tx: in_tx }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heard(events: &[SocketEvent]) -> Vec<ConnectionState> {
+        let heard = Rc::new(RefCell::new(vec![]));
+        let listener = {
+            let heard = Rc::clone(&heard);
+            Some(Callback::from(move |state| heard.borrow_mut().push(state)))
+        };
+        for event in events {
+            notify(&listener, *event);
+        }
+        let heard = heard.borrow().clone();
+        heard
+    }
+
+    #[test]
+    fn open_then_close_reports_connected_then_disconnected() {
+        assert_eq!(
+            heard(&[
+                SocketEvent::SinkSettled { opened: true },
+                SocketEvent::StreamEnded
+            ]),
+            vec![ConnectionState::Connected, ConnectionState::Disconnected]
+        );
+    }
+
+    #[test]
+    fn a_socket_that_never_opens_only_reports_disconnected() {
+        assert_eq!(
+            heard(&[
+                SocketEvent::SinkSettled { opened: false },
+                SocketEvent::StreamEnded
+            ]),
+            vec![ConnectionState::Disconnected]
+        );
+    }
 }
\ No newline at end of file